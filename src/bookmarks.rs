@@ -0,0 +1,91 @@
+use std::string;
+
+// Bookmarks closer than this to the current position are skipped when looking for the next or previous one,
+// so that going back repeatedly doesn't stick to the bookmark that was just reached.
+const SKIP_THRESHOLD: gst::ClockTime = gst::ClockTime::from_seconds(1);
+
+/// A moment of a media the user wants to come back to.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub position: gst::ClockTime,
+    #[serde(default)]
+    pub label: Option<string::String>,
+}
+
+impl Bookmark {
+    pub fn new(position: gst::ClockTime, label: Option<string::String>) -> Self {
+        Self { position, label }
+    }
+
+    pub fn display_label(&self) -> string::String {
+        match self.label {
+            Some(ref label) => format!("{:.0} {label}", self.position),
+            None => format!("{:.0}", self.position),
+        }
+    }
+}
+
+/// Add a bookmark to a list sorted by position. Returns its index.
+pub fn insert(bookmarks: &mut Vec<Bookmark>, bookmark: Bookmark) -> usize {
+    let index = bookmarks.partition_point(|b| b.position <= bookmark.position);
+    bookmarks.insert(index, bookmark);
+    index
+}
+
+pub fn next(bookmarks: &[Bookmark], position: gst::ClockTime) -> Option<&Bookmark> {
+    bookmarks.iter().find(|b| b.position > position + SKIP_THRESHOLD)
+}
+
+pub fn previous(bookmarks: &[Bookmark], position: gst::ClockTime) -> Option<&Bookmark> {
+    bookmarks.iter().rev().find(|b| b.position + SKIP_THRESHOLD < position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(s: u64) -> gst::ClockTime {
+        gst::ClockTime::from_seconds(s)
+    }
+
+    #[test]
+    fn sorted_insertion() {
+        let mut bookmarks = vec![];
+        assert_eq!(insert(&mut bookmarks, Bookmark::new(seconds(60), None)), 0);
+        assert_eq!(
+            insert(&mut bookmarks, Bookmark::new(seconds(10), Some("Intro".into()))),
+            0
+        );
+        assert_eq!(insert(&mut bookmarks, Bookmark::new(seconds(90), None)), 2);
+        assert_eq!(
+            insert(&mut bookmarks, Bookmark::new(seconds(60), Some("Again".into()))),
+            2
+        );
+        let positions: Vec<_> = bookmarks.iter().map(|b| b.position.seconds()).collect();
+        assert_eq!(positions, [10, 60, 60, 90]);
+        assert_eq!(bookmarks[0].display_label(), "0:00:10 Intro");
+        assert_eq!(bookmarks[1].display_label(), "0:01:00");
+    }
+
+    #[test]
+    fn next_and_previous() {
+        let bookmarks = vec![
+            Bookmark::new(seconds(10), None),
+            Bookmark::new(seconds(60), None),
+            Bookmark::new(seconds(90), None),
+        ];
+        let next = |s| next(&bookmarks, seconds(s)).map(|b| b.position.seconds());
+        let previous = |s| previous(&bookmarks, seconds(s)).map(|b| b.position.seconds());
+
+        assert_eq!(next(0), Some(10));
+        assert_eq!(next(30), Some(60));
+        // Just jumped to that one.
+        assert_eq!(next(60), Some(90));
+        assert_eq!(next(90), None);
+
+        assert_eq!(previous(100), Some(90));
+        assert_eq!(previous(75), Some(60));
+        assert_eq!(previous(60), Some(10));
+        assert_eq!(previous(10), None);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::media_cache::{self, MediaCache, MediaRecord};
+use crate::playlist::PlaylistItem;
+use gio::prelude::*;
+use gstreamer::glib;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path;
+use std::string;
+
+/// Maintenance of the playback history, run without starting the user interface.
+#[derive(clap::Subcommand, Debug)]
+pub enum CacheCommand {
+    /// List the remembered media, most recently played first
+    List,
+    /// Forget everything remembered about a media
+    Forget {
+        /// File or URI of the media
+        #[clap(name = "FILE|URI")]
+        target: string::String,
+    },
+    /// Forget all media, except the ones with bookmarks
+    Clear {
+        /// Forget the media with bookmarks too
+        #[clap(long)]
+        bookmarks: bool,
+    },
+    /// Forget deleted files and media not played for a long time, and remove unused covers
+    Prune,
+    /// Write the playback history as JSON to a file, or to the standard output
+    Export {
+        #[clap(name = "FILE")]
+        path: Option<path::PathBuf>,
+    },
+    /// Merge a playback history written by `glide cache export`, from a file or from the standard input.
+    /// Media played more recently on this machine are kept
+    Import {
+        #[clap(name = "FILE")]
+        path: Option<path::PathBuf>,
+    },
+}
+
+pub fn run(command: CacheCommand, cache_path: &path::Path) -> anyhow::Result<()> {
+    let mut cache = MediaCache::read(cache_path).or_else(|e| {
+        if cache_path.exists() {
+            Err(anyhow::anyhow!("Unable to read {}: {e}", cache_path.display()))
+        } else {
+            Ok(MediaCache::open(cache_path))
+        }
+    })?;
+    if cache.is_read_only() && !matches!(command, CacheCommand::List | CacheCommand::Export { .. }) {
+        anyhow::bail!(
+            "{} was written by a newer release of Glide, it can't be modified",
+            cache_path.display()
+        );
+    }
+
+    // The output is often piped to other commands, which might not read all of it.
+    match execute(command, &mut cache, &mut io::stdout().lock()) {
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        result => result,
+    }
+}
+
+fn execute(command: CacheCommand, cache: &mut MediaCache, stdout: &mut impl Write) -> anyhow::Result<()> {
+    match command {
+        CacheCommand::List => {
+            for record in cache.records() {
+                writeln!(stdout, "{}", list_line(record))?;
+            }
+        }
+        CacheCommand::Forget { target } => {
+            let uri = gio::File::for_commandline_arg(&target).uri();
+            // Also finds the record of a file renamed since it was played.
+            cache.set_fingerprint(&uri, media_cache::local_fingerprint(&uri));
+            let Some(record) = cache.forget(&uri) else {
+                anyhow::bail!("Nothing is remembered about {uri}");
+            };
+            cache.write()?;
+            media_cache::remove_covers(&media_cache::covers_dir(), &[record])?;
+        }
+        CacheCommand::Clear { bookmarks } => {
+            let records = cache.clear(bookmarks);
+            cache.write()?;
+            media_cache::remove_covers(&media_cache::covers_dir(), &records)?;
+            writeln!(stdout, "Forgot {} media", records.len())?;
+            if cache.len() > 0 {
+                writeln!(
+                    stdout,
+                    "Kept {} media with bookmarks, use --bookmarks to forget them too",
+                    cache.len()
+                )?;
+            }
+        }
+        CacheCommand::Prune => {
+            let stats = cache.prune()?;
+            writeln!(
+                stdout,
+                "Removed {} deleted files, {} stale entries and {} unused covers, {} entries left",
+                stats.missing,
+                stats.evicted,
+                stats.covers,
+                cache.len()
+            )?;
+        }
+        CacheCommand::Export { path } => match path {
+            Some(path) => cache.export(File::create(path)?)?,
+            None => cache.export(stdout)?,
+        },
+        CacheCommand::Import { path } => {
+            let count = match path {
+                Some(path) => cache.import(File::open(path)?)?,
+                None => cache.import(io::stdin().lock())?,
+            };
+            cache.write()?;
+            writeln!(stdout, "Imported {count} media, {} remembered", cache.len())?;
+        }
+    }
+    Ok(())
+}
+
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let kind = match cause.downcast_ref::<serde_json::Error>() {
+            Some(e) => e.io_error_kind(),
+            None => cause.downcast_ref::<io::Error>().map(io::Error::kind),
+        };
+        kind == Some(io::ErrorKind::BrokenPipe)
+    })
+}
+
+// Tab-separated last played date, position, title and URI, for the media of older releases only the
+// position is known.
+fn list_line(record: &MediaRecord) -> string::String {
+    let last_played = record
+        .last_played
+        .and_then(|seconds| glib::DateTime::from_unix_local(seconds as i64).ok())
+        .and_then(|date| date.format("%Y-%m-%d %H:%M").ok())
+        .map(|date| date.to_string())
+        .unwrap_or_else(|| "-".into());
+    let position = match (record.position, record.duration) {
+        (Some(position), Some(duration)) => format!("{position:.0} / {duration:.0}"),
+        (Some(position), None) => format!("{position:.0}"),
+        _ => "-".into(),
+    };
+    let uri = record.uri.clone().unwrap_or_else(|| "-".into());
+    let title = match record.title {
+        Some(ref title) => title.clone(),
+        None if record.uri.is_some() => PlaylistItem::new(uri.clone()).display_title(),
+        None => "-".into(),
+    };
+    format!("{last_played}\t{position}\t{title}\t{uri}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bookmarks::Bookmark;
+    use std::fs;
+
+    fn execute_to_string(command: CacheCommand, cache: &mut MediaCache) -> anyhow::Result<string::String> {
+        let mut output = vec![];
+        execute(command, cache, &mut output)?;
+        Ok(string::String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn forget_by_file_and_uri() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("media-cache.json");
+        let file = dir.path().join("a.mkv");
+        fs::write(&file, [1u8; 16]).unwrap();
+        let file_uri = gio::File::for_path(&file).uri();
+        let mut cache = MediaCache::open(&path);
+        cache.mark_played(&file_uri);
+        cache.mark_played("https://example.com/b.mkv");
+
+        let forget = |target: &str| CacheCommand::Forget { target: target.into() };
+        execute_to_string(forget(&file.to_string_lossy()), &mut cache).unwrap();
+        assert!(cache.record(&file_uri).is_none());
+        execute_to_string(forget("https://example.com/b.mkv"), &mut cache).unwrap();
+        assert!(execute_to_string(forget("https://example.com/b.mkv"), &mut cache).is_err());
+        assert_eq!(MediaCache::read(&path).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("media-cache.json");
+        let mut cache = MediaCache::open(&path);
+        cache.mark_played("file:///a.mkv");
+        cache.update("file:///b.mkv", |record| {
+            record
+                .bookmarks
+                .push(Bookmark::new(gst::ClockTime::from_seconds(1), None))
+        });
+
+        let output = execute_to_string(CacheCommand::Clear { bookmarks: false }, &mut cache).unwrap();
+        assert_eq!(
+            output,
+            "Forgot 1 media\nKept 1 media with bookmarks, use --bookmarks to forget them too\n"
+        );
+        assert!(MediaCache::read(&path).unwrap().record("file:///b.mkv").is_some());
+        let output = execute_to_string(CacheCommand::Clear { bookmarks: true }, &mut cache).unwrap();
+        assert_eq!(output, "Forgot 1 media\n");
+        assert_eq!(MediaCache::read(&path).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn export_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open(dir.path().join("media-cache.json"));
+        cache.mark_played("file:///a.mkv");
+        cache.update("file:///b.mkv", |record| record.rate = Some(1.5));
+        let exported = dir.path().join("export.json");
+        let command = CacheCommand::Export {
+            path: Some(exported.clone()),
+        };
+        execute_to_string(command, &mut cache).unwrap();
+
+        let other_path = dir.path().join("other.json");
+        let mut other = MediaCache::open(&other_path);
+        let command = CacheCommand::Import { path: Some(exported) };
+        let output = execute_to_string(command, &mut other).unwrap();
+        assert_eq!(output, "Imported 2 media, 2 remembered\n");
+        let other = MediaCache::read(&other_path).unwrap();
+        for uri in ["file:///a.mkv", "file:///b.mkv"] {
+            assert_eq!(other.record(uri), cache.record(uri));
+        }
+    }
+
+    #[test]
+    fn import_rejects_unknown_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let exported = dir.path().join("export.json");
+        fs::write(&exported, r#"{"version": 999, "media": {}}"#).unwrap();
+        let path = dir.path().join("media-cache.json");
+        let mut cache = MediaCache::open(&path);
+        cache.mark_played("file:///a.mkv");
+
+        let command = CacheCommand::Import { path: Some(exported) };
+        assert!(execute_to_string(command, &mut cache).is_err());
+        assert_eq!(cache.len(), 1);
+        assert!(!path.exists());
+    }
+}
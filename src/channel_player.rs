@@ -7,6 +7,8 @@ extern crate sha2;
 extern crate tar;
 
 use self::sha2::{Digest, Sha256};
+use crate::bookmarks::{self, Bookmark};
+use crate::chapters::{self, Chapter};
 use crate::config;
 use crate::debug_infos::DebugInfos;
 use crate::gio::prelude::ActionExt;
@@ -15,6 +17,12 @@ use crate::gio::prelude::FileExt;
 use crate::gio::prelude::OutputStreamExt;
 use crate::gst_play::prelude::PlayStreamInfoExt;
 use crate::gtk::prelude::PaintableExt;
+use crate::media_cache::{self, MediaCache, MediaRecord};
+use crate::media_directory;
+use crate::playlist::{LoopMode, Playlist, PlaylistItem};
+use crate::playlist_format;
+use crate::session::Session;
+use crate::settings::{self, ResumeMode, SubtitleStyle};
 use async_lock::OnceCell as AsyncOnceCell;
 use gio::prelude::{ActionGroupExt, ActionMapExt};
 use graphviz_rust::{
@@ -31,8 +39,8 @@ use gtk::gdk;
 use gtk::glib::clone;
 use mpris_server::{
     zbus::{self, fdo},
-    LocalPlayerInterface, LocalRootInterface, LocalServer, LoopStatus, Metadata, PlaybackRate, PlaybackStatus,
-    Property, Signal, Time, TrackId, Volume,
+    LocalPlayerInterface, LocalRootInterface, LocalServer, LocalTrackListInterface, LoopStatus, Metadata, PlaybackRate,
+    PlaybackStatus, Property, Signal, Time, TrackId, TrackListSignal, Uri, Volume,
 };
 use std::borrow::BorrowMut;
 use std::cell::RefCell;
@@ -41,6 +49,7 @@ use std::fs::File;
 use std::io::{Read, Seek, Write};
 use std::path;
 use std::string;
+use std::sync::{Arc, Mutex};
 use tar::Builder;
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
@@ -51,6 +60,26 @@ pub enum PlaybackState {
     Playing,
 }
 
+impl From<LoopMode> for LoopStatus {
+    fn from(loop_mode: LoopMode) -> Self {
+        match loop_mode {
+            LoopMode::None => LoopStatus::None,
+            LoopMode::Track => LoopStatus::Track,
+            LoopMode::Playlist => LoopStatus::Playlist,
+        }
+    }
+}
+
+impl From<LoopStatus> for LoopMode {
+    fn from(loop_status: LoopStatus) -> Self {
+        match loop_status {
+            LoopStatus::None => LoopMode::None,
+            LoopStatus::Track => LoopMode::Track,
+            LoopStatus::Playlist => LoopMode::Playlist,
+        }
+    }
+}
+
 impl PlaybackState {
     fn to_playback_status(self) -> PlaybackStatus {
         match self {
@@ -87,6 +116,17 @@ pub enum PlayerEvent {
     AudioVideoOffsetChanged(i64),
     SubtitleVideoOffsetChanged(i64),
     SeekDone,
+    LoopModeChanged(LoopMode),
+    ShuffleChanged(bool),
+    PlaylistChanged,
+    CacheWriteFailed(String),
+    // Position the media can be resumed from, if the user wants to.
+    ResumeAvailable(Option<gst::ClockTime>),
+    ChaptersChanged,
+    // Start and end of the A–B repeat.
+    AbRepeatChanged(Option<gst::ClockTime>, Option<gst::ClockTime>),
+    // A seek left the A–B loop, which was cleared.
+    AbRepeatCancelled,
 }
 
 #[derive(Clone)]
@@ -104,20 +144,113 @@ impl Drop for ChannelPlayer {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct MediaCacheData(pub HashMap<string::String, u64>);
+// Shared with the streaming thread, which queues the next playlist item when the current one is about to finish.
+#[derive(Default)]
+struct GaplessState {
+    // Only audio media are chained without a gap, others go through EOS and a regular URI change. This is
+    // whether the current media is one, `next` is only set for items that look like audio files.
+    enabled: bool,
+    next: Option<(u64, string::String)>,
+    queued: Option<(u64, string::String)>,
+}
 
-struct MediaCache {
-    path: path::PathBuf,
-    data: MediaCacheData,
+// Looped part of the media, shared with the streaming thread which goes back to A when the segment ends at B.
+#[derive(Clone, Copy, Default, PartialEq)]
+struct AbRepeat {
+    a: Option<gst::ClockTime>,
+    b: Option<gst::ClockTime>,
+    // Playback rate changed while looping. GstPlay isn't told about it, its own seek would end the segment.
+    rate: Option<f64>,
+}
+
+impl AbRepeat {
+    // Only looped once both ends are set.
+    fn segment(&self) -> Option<(gst::ClockTime, gst::ClockTime)> {
+        Some((self.a?, self.b?))
+    }
+}
+
+// Play from `start` until the end of the segment, where the pipeline posts a segment-done message instead of
+// EOS. Without flushing, the new segment follows the data already queued without a gap.
+fn seek_segment(player: &gst_play::Play, rate: f64, start: gst::ClockTime, end: gst::ClockTime, flush: bool) {
+    let mut flags = gst::SeekFlags::SEGMENT | gst::SeekFlags::ACCURATE;
+    if flush {
+        flags |= gst::SeekFlags::FLUSH;
+    }
+    let result = player
+        .pipeline()
+        .seek(rate, flags, gst::SeekType::Set, start, gst::SeekType::Set, end);
+    if let Err(e) = result {
+        eprintln!("Unable to loop from {start} to {end}: {e}");
+    }
+}
+
+// Move the pipeline of the track fading out to `position`, played at `rate`.
+fn seek_crossfade(outgoing: &gst::Element, rate: f64, position: gst::ClockTime) {
+    let flags = gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE;
+    let result = outgoing.seek(
+        rate,
+        flags,
+        gst::SeekType::Set,
+        position,
+        gst::SeekType::None,
+        gst::ClockTime::NONE,
+    );
+    if let Err(e) = result {
+        eprintln!("Unable to crossfade at {position}: {e}");
+    }
+}
+
+// Text overlays are created by the subtitle overlay of playbin when text subtitles are enabled. The font is set
+// through playbin, which would otherwise reset it on the overlay.
+fn apply_subtitle_style(overlay: &gst::Element, style: &SubtitleStyle) {
+    let color = |color: &str, default| settings::color_to_argb(color).unwrap_or(default);
+    overlay.set_property("color", color(&style.text_color, 0xffffffff));
+    overlay.set_property("draw-outline", style.outline);
+    overlay.set_property("outline-color", color(&style.outline_color, 0xff000000));
+    overlay.set_property("shaded-background", style.background_opacity > 0.0);
+    overlay.set_property(
+        "shading-value",
+        ((style.background_opacity * 255.0).round() as u32).clamp(1, 255),
+    );
+    overlay.set_property("ypad", style.margin as i32);
+}
+
+// Outgoing track of a crossfade, played by a separate pipeline while the main player plays the incoming one.
+struct Crossfade {
+    outgoing: gst::Element,
+    #[allow(dead_code)]
+    bus_watch: gst::bus::BusWatchGuard,
+    volume: f64,
+    started: Option<std::time::Instant>,
+    timeout: Option<glib::SourceId>,
+    // Set once the incoming track is louder than the outgoing one. Until then media informations of the
+    // incoming track are not announced.
+    dominant: bool,
 }
 
 struct PlayerDataHolder {
     subscribers: Vec<async_channel::Sender<PlayerEvent>>,
-    playlist: Vec<string::String>,
+    playlist: Playlist,
     current_uri: glib::GString,
-    index: usize,
+    gapless: Arc<Mutex<GaplessState>>,
+    // URI chained by the pipeline after the one GstPlay loaded, which it is not aware of.
+    gapless_uri: Option<glib::GString>,
+    crossfade_duration: gst::ClockTime,
+    crossfade: Option<Crossfade>,
+    // Audio filter of the main pipeline, fading the incoming track in.
+    fade_volume: gst::Element,
     cache: Option<MediaCache>,
+    // Changes to the cache are written together, shortly after the first one or when playback pauses.
+    cache_write_source: Option<glib::SourceId>,
+    cache_write_failed: bool,
+    resume_mode: ResumeMode,
+    pending_resume: Option<gst::ClockTime>,
+    chapters: Vec<Chapter>,
+    // Chapters of the item queued for a gapless transition, read before it starts playing.
+    queued_chapters: Option<Vec<Chapter>>,
+    ab_repeat: Arc<Mutex<AbRepeat>>,
+    subtitle_style: Arc<Mutex<SubtitleStyle>>,
     #[allow(dead_code)]
     bus_watch: gst::bus::BusWatchGuard,
     state: PlaybackState,
@@ -167,65 +300,73 @@ fn emit_mpris_signal(signal: Signal) {
     });
 }
 
-impl MediaCache {
-    fn open<T: Copy + Into<path::PathBuf>>(path: T) -> anyhow::Result<Self> {
-        MediaCache::read(path.into()).or_else(|_| {
-            Ok(Self {
-                path: path.into(),
-                data: MediaCacheData(HashMap::new()),
-            })
-        })
-    }
-
-    fn read<T: AsRef<path::Path> + Into<path::PathBuf>>(path: T) -> anyhow::Result<Self> {
-        let mut file = File::open(path.as_ref())?;
-        let mut data = String::new();
-        file.read_to_string(&mut data).unwrap();
+fn emit_mpris_track_list_signal(signal: TrackListSignal) {
+    MPRIS_SERVER.with(|server| {
+        if let Some(server) = server.get() {
+            let _ = glib::MainContext::default().block_on(server.track_list_emit(signal));
+        }
+    });
+}
 
-        let json: MediaCacheData = serde_json::from_str(&data)?;
-        Ok(Self {
-            path: path.into(),
-            data: json,
-        })
-    }
+const TRACK_ID_PREFIX: &str = "/net/base_art/Glide/Track/";
+// Writing the cache locks and reads it back, to merge the changes of other instances.
+const CACHE_WRITE_DELAY_SECONDS: u32 = 5;
 
-    fn update<K: Into<String>>(&mut self, id: K, value: u64) {
-        self.data.0.insert(id.into(), value);
-    }
+fn track_id(item: &PlaylistItem) -> TrackId {
+    TrackId::try_from(format!("{TRACK_ID_PREFIX}{}", item.id)).unwrap()
+}
 
-    fn write(&self) -> anyhow::Result<()> {
-        let mut file = File::create(&self.path)?;
+fn playlist_item_id(track_id: &TrackId) -> Option<u64> {
+    track_id.as_str().strip_prefix(TRACK_ID_PREFIX)?.parse().ok()
+}
 
-        let json = serde_json::to_string(&self.data)?;
-        file.write_all(json.as_bytes())?;
-        file.sync_all()?;
-        Ok(())
+fn track_metadata(item: &PlaylistItem) -> Metadata {
+    let mut builder = Metadata::builder()
+        .trackid(track_id(item))
+        .url(item.uri.clone())
+        .title(item.display_title());
+    if let Some(duration) = item.duration {
+        builder = builder.length(Time::from_micros(duration.useconds() as i64));
     }
+    builder.build()
+}
 
-    fn find_last_position(&self, uri: &str) -> Option<gst::ClockTime> {
-        let id = uri_to_sha256(uri);
-        if let Some(position) = self.data.0.get(&id) {
-            return Some(gst::ClockTime::from_nseconds(*position));
-        }
-
-        None
-    }
+// URI schemes handled by the source elements available in the GStreamer registry.
+fn supported_uri_schemes() -> Vec<string::String> {
+    let mut schemes: Vec<string::String> =
+        gst::ElementFactory::factories_with_type(gst::ElementFactoryType::SRC, gst::Rank::MARGINAL)
+            .iter()
+            .filter(|factory| factory.uri_type() == gst::URIType::Src)
+            .flat_map(|factory| factory.uri_protocols())
+            .map(|protocol| protocol.to_string())
+            .collect();
+    schemes.sort();
+    schemes.dedup();
+    schemes
 }
 
-fn uri_to_sha256(uri: &str) -> string::String {
-    let mut sh = Sha256::new();
-    sh.update(uri.as_bytes());
-    sh.finalize()
-        .into_iter()
-        .map(|b| format!("{b:02x}"))
-        .collect::<Vec<_>>()
-        .concat()
+// Container formats the demuxers available in the GStreamer registry can handle.
+fn supported_mime_types() -> Vec<string::String> {
+    let mut mime_types = vec![];
+    for factory in gst::ElementFactory::factories_with_type(gst::ElementFactoryType::DEMUXER, gst::Rank::MARGINAL) {
+        for template in factory.static_pad_templates() {
+            if template.direction() != gst::PadDirection::Sink {
+                continue;
+            }
+            let caps = template.caps();
+            if caps.is_any() {
+                continue;
+            }
+            mime_types.extend(caps.iter().map(|structure| structure.name().to_string()));
+        }
+    }
+    mime_types.sort();
+    mime_types.dedup();
+    mime_types
 }
 
 fn cache_cover_art(data: &[u8]) -> Option<gio::File> {
-    let mut cache_dir = glib::user_cache_dir();
-    cache_dir.push("glide");
-    cache_dir.push("covers");
+    let mut cache_dir = media_cache::covers_dir();
     glib::mkdir_with_parents(&cache_dir, 0o755);
 
     let mut sh = Sha256::new();
@@ -257,10 +398,46 @@ fn cache_cover_art(data: &[u8]) -> Option<gio::File> {
     Some(file)
 }
 
+// Give the media cache the fingerprint of a local file, computed in a separate thread. `f` is then called from
+// the main loop, also when no fingerprint is needed.
+fn with_fingerprint<F: FnOnce() + 'static>(player: &gst_play::Play, uri: &str, f: F) {
+    let player = player.clone();
+    let uri = uri.to_string();
+    glib::MainContext::default().spawn_local(async move {
+        let mut needed = false;
+        with_player!(player player_data {
+            needed = player_data.cache.as_ref().is_some_and(|cache| cache.needs_fingerprint(&uri));
+        });
+        if needed {
+            let path = uri.clone();
+            let fingerprint = gio::spawn_blocking(move || media_cache::local_fingerprint(&path))
+                .await
+                .ok()
+                .flatten();
+            with_mut_player!(player player_data {
+                if let Some(ref mut cache) = player_data.cache {
+                    cache.set_fingerprint(&uri, fingerprint);
+                }
+            });
+        }
+        f();
+    });
+}
+
+// Apply the settings remembered for a media before it starts playing.
+fn start_loaded_media(player: &gst_play::Play, uri: &str) {
+    player.pause();
+    with_mut_player!(player player_data {
+        player_data.uri_loaded(player, uri);
+    });
+    player.play();
+}
+
 impl PlayerDataHolder {
-    fn set_playlist(&mut self, playlist: Vec<string::String>) {
-        self.playlist = playlist;
-        self.index = 0;
+    fn set_playlist(&mut self, playlist: Vec<PlaylistItem>) {
+        self.playlist.set_items(playlist);
+        self.playlist_changed();
+        self.track_list_replaced();
     }
 
     #[allow(dead_code)]
@@ -270,6 +447,10 @@ impl PlayerDataHolder {
 
     fn set_state(&mut self, state: PlaybackState) {
         self.state = state;
+        // Buffering happens many times per second on network streams, it isn't worth a write.
+        if matches!(state, PlaybackState::Paused | PlaybackState::Stopped) {
+            self.write_cache();
+        }
         mpris_properties_changed([Property::PlaybackStatus(self.state.to_playback_status())]);
     }
 
@@ -283,14 +464,67 @@ impl PlayerDataHolder {
         });
     }
 
+    fn set_ab_repeat(&self, ab_repeat: AbRepeat) {
+        let mut current = self.ab_repeat.lock().unwrap();
+        let changed = (current.a, current.b) != (ab_repeat.a, ab_repeat.b);
+        *current = ab_repeat;
+        if changed {
+            self.notify(PlayerEvent::AbRepeatChanged(ab_repeat.a, ab_repeat.b));
+        }
+    }
+
+    fn playback_rate(&self, player: &gst_play::Play) -> f64 {
+        self.ab_repeat.lock().unwrap().rate.unwrap_or_else(|| player.rate())
+    }
+
+    // Seeks of GstPlay end the looped segment. Seeking within the loop is done with a single segment seek
+    // instead, seeking out of it cancels the loop.
+    fn seek(&self, player: &gst_play::Play, position: gst::ClockTime) {
+        let ab_repeat = *self.ab_repeat.lock().unwrap();
+        if let Some((a, b)) = ab_repeat.segment() {
+            if (a..b).contains(&position) {
+                seek_segment(player, self.playback_rate(player), position, b, true);
+                self.seek_done(&position);
+                self.notify(PlayerEvent::SeekDone);
+                return;
+            }
+            self.set_ab_repeat(AbRepeat::default());
+            self.notify(PlayerEvent::AbRepeatCancelled);
+            // Both are applied by the same seek.
+            if let Some(rate) = ab_repeat.rate {
+                player.set_rate(rate);
+            }
+        }
+        player.seek(position);
+    }
+
+    // Changing the rate of GstPlay seeks too, while looping the segment is restarted at the new rate instead.
+    fn set_playback_rate(&self, player: &gst_play::Play, rate: f64) {
+        let mut ab_repeat = self.ab_repeat.lock().unwrap();
+        if let (Some((_, b)), Some(position)) = (ab_repeat.segment(), player.position()) {
+            ab_repeat.rate = Some(rate);
+            drop(ab_repeat);
+            seek_segment(player, rate, position, b, true);
+        } else {
+            drop(ab_repeat);
+            player.set_rate(rate);
+        }
+        // The track fading out keeps up with the one fading in.
+        if let Some(crossfade) = self.crossfade.as_ref().filter(|crossfade| crossfade.started.is_some()) {
+            if let Some(position) = crossfade.outgoing.query_position::<gst::ClockTime>() {
+                seek_crossfade(&crossfade.outgoing, rate, position);
+            }
+        }
+    }
+
     fn notify(&self, event: PlayerEvent) {
         for sender in &*self.subscribers {
             let _ = sender.send_blocking(event.clone());
         }
     }
 
-    fn update_mpris_metadata(&mut self, info: &gst_play::PlayMediaInfo) {
-        let mut builder = Metadata::builder().url(info.uri());
+    fn update_mpris_metadata(&mut self, info: &gst_play::PlayMediaInfo, uri: &str) {
+        let mut builder = Metadata::builder().url(uri);
         if let Some(audio_info) = info.audio_streams().first() {
             if let Some(tags) = audio_info.tags() {
                 if let Some(album_title) = tags.get::<gst::tags::Album>() {
@@ -326,13 +560,17 @@ impl PlayerDataHolder {
                     let data = mapped_buffer.as_slice();
                     if let Some(path) = cache_cover_art(data) {
                         builder = builder.art_url(path.uri());
+                        if let (Some(ref mut cache), Some(name)) = (&mut self.cache, path.basename()) {
+                            cache.update(uri, |record| record.cover = Some(name.to_string_lossy().into()));
+                        }
                     }
                 }
             }
         }
 
-        let title = info.title().or_else(|| {
-            if let Ok((path, _)) = glib::filename_from_uri(&info.uri()) {
+        let item_title = self.current_item().and_then(|item| item.title.clone());
+        let title = info.title().or(item_title.map(glib::GString::from)).or_else(|| {
+            if let Ok((path, _)) = glib::filename_from_uri(uri) {
                 let path = std::path::Path::new(&path);
                 path.file_name()
                     .map(|v| glib::GString::from_string_unchecked(v.to_string_lossy().to_string()))
@@ -344,7 +582,10 @@ impl PlayerDataHolder {
         if let Some(title) = title {
             builder = builder.title(title);
         }
-        if let Some(duration) = info.duration() {
+        if let Some(item) = self.current_item() {
+            builder = builder.trackid(track_id(item));
+        }
+        if let Some(duration) = info.duration().or(self.current_item().and_then(|item| item.duration)) {
             builder = builder.length(Time::from_micros(duration.useconds() as i64));
         }
         let metadata = builder.build();
@@ -353,18 +594,65 @@ impl PlayerDataHolder {
     }
 
     fn media_info_updated(&mut self, info: &gst_play::PlayMediaInfo) {
-        let uri = info.uri();
+        if self.crossfade.as_ref().is_some_and(|crossfade| !crossfade.dominant) {
+            return;
+        }
+        let uri = self.gapless_uri.clone().unwrap_or_else(|| info.uri());
+        self.gapless.lock().unwrap().enabled = info.number_of_video_streams() == 0;
 
         // Call this only once per asset.
-        if self.current_uri != *uri {
-            self.current_uri = uri;
+        let is_new_media = self.current_uri != uri;
+        if is_new_media {
+            self.current_uri = uri.clone();
             self.notify(PlayerEvent::MediaInfoUpdated);
-            self.update_mpris_metadata(info);
+            self.update_mpris_metadata(info, &uri);
             self.seekable = info.is_seekable();
         }
+        if let Some(ref mut cache) = self.cache {
+            if is_new_media {
+                cache.mark_played(&uri);
+            }
+            cache.update(&uri, |record| {
+                if let Some(title) = info.title() {
+                    record.title = Some(title.into());
+                }
+                if let Some(duration) = info.duration() {
+                    record.duration = Some(duration);
+                }
+            });
+        }
+        self.update_current_item(info, &uri);
+    }
+
+    // Keep the playlist entry in sync with the media informations, so it can be saved along with it.
+    fn update_current_item(&mut self, info: &gst_play::PlayMediaInfo, uri: &str) {
+        let Some(item) = self.playlist.current_mut() else {
+            return;
+        };
+        if item.uri != uri {
+            return;
+        }
+        let previous_item = item.clone();
+        if let Some(title) = info.title() {
+            item.title = Some(title.into());
+        }
+        if let Some(duration) = info.duration() {
+            item.duration = Some(duration);
+        }
+        if *item != previous_item {
+            let signal = TrackListSignal::TrackMetadataChanged {
+                track_id: track_id(item),
+                metadata: track_metadata(item),
+            };
+            self.playlist_changed();
+            glib::idle_add_local_once(move || emit_mpris_track_list_signal(signal));
+        }
     }
 
     fn duration_changed(&mut self, duration: Option<gst::ClockTime>) {
+        if self.crossfade.as_ref().is_some_and(|crossfade| !crossfade.dominant) {
+            return;
+        }
         self.notify(PlayerEvent::DurationChanged(duration));
         if let Some(duration) = duration {
             self.metadata
@@ -375,46 +663,502 @@ impl PlayerDataHolder {
     }
 
     fn end_of_stream(&mut self, player: &gst_play::Play) {
-        if let Some(uri) = player.uri() {
+        if let Some(uri) = self.playing_uri(player) {
             self.notify(PlayerEvent::EndOfStream(uri.into()));
-            let _ = self.go_next(player);
+            let previous_index = self.playlist.index();
+            if self.playlist.advance().is_some() {
+                self.playlist_changed();
+                self.load_current_item(player, previous_index);
+            } else {
+                self.notify(PlayerEvent::EndOfPlaylist);
+            }
         }
     }
 
     fn go_next(&mut self, player: &gst_play::Play) -> bool {
-        self.index += 1;
-
-        self.update_mpris_nav_controls();
-        if self.index < self.playlist_length() {
-            let next_uri = &*self.playlist[self.index];
-            player.set_property("uri", next_uri);
-            return true;
+        let previous_index = self.playlist.index();
+        if self.playlist.go_next().is_none() {
+            self.notify(PlayerEvent::EndOfPlaylist);
+            return false;
         }
-        self.notify(PlayerEvent::EndOfPlaylist);
-        false
+        self.playlist_changed();
+        self.load_current_item(player, previous_index);
+        true
     }
 
     fn go_prev(&mut self, player: &gst_play::Play) -> bool {
-        if self.index < 1 {
+        let previous_index = self.playlist.index();
+        if self.playlist.go_prev().is_none() {
+            return false;
+        }
+        self.playlist_changed();
+        self.load_current_item(player, previous_index);
+        true
+    }
+
+    fn jump_to(&mut self, player: &gst_play::Play, index: usize) -> bool {
+        let previous_index = self.playlist.index();
+        if self.playlist.jump(index).is_none() {
             return false;
         }
-        self.index -= 1;
+        self.playlist_changed();
+        self.load_current_item(player, previous_index);
+        true
+    }
+
+    fn remove_item(&mut self, player: &gst_play::Play, index: usize) {
+        let was_current = index == self.playlist.index();
+        let Some(item) = self.playlist.remove(index) else {
+            return;
+        };
+        self.playlist_changed();
+        let signal = TrackListSignal::TrackRemoved {
+            track_id: track_id(&item),
+        };
+        glib::idle_add_local_once(move || emit_mpris_track_list_signal(signal));
+        if !was_current {
+            return;
+        }
+        match self.playlist.current().map(|item| item.uri.clone()) {
+            Some(uri) => self.load_uri(player, &uri),
+            None => player.stop(),
+        };
+    }
+
+    fn move_item(&mut self, from: usize, to: usize) {
+        if self.playlist.move_item(from, to) {
+            self.playlist_changed();
+            self.track_list_replaced();
+        }
+    }
+
+    fn play_next(&mut self, index: usize) {
+        if self.playlist.play_next(index) {
+            self.playlist_changed();
+            // Only the play order changes when shuffling, the track list stays as it was.
+            if !self.playlist.is_shuffled() {
+                self.track_list_replaced();
+            }
+        }
+    }
+
+    // Insert a new item after the one with the given identifier, or at the start of the playlist.
+    fn add_item(&mut self, player: &gst_play::Play, uri: &str, after: Option<u64>, set_as_current: bool) -> bool {
+        let index = match after {
+            Some(id) => match self.playlist.position_of(id) {
+                Some(position) => position + 1,
+                None => return false,
+            },
+            None => 0,
+        };
+        let was_empty = self.playlist.is_empty();
+        self.insert_item(index, PlaylistItem::new(uri));
+        if set_as_current && !was_empty {
+            self.jump_to(player, index);
+            return true;
+        }
+        self.playlist_changed();
+        if set_as_current {
+            self.load_uri(player, uri);
+        }
+        true
+    }
+
+    // Add new entries at the end of the playlist, or right after the current one, leaving playback untouched.
+    fn enqueue(&mut self, items: Vec<PlaylistItem>, play_next: bool) {
+        if play_next {
+            for item in items.into_iter().rev() {
+                let index = self.insert_item(self.playlist.index() + 1, item);
+                self.playlist.play_next(index);
+            }
+        } else {
+            for item in items {
+                self.insert_item(self.playlist.len(), item);
+            }
+        }
+        self.playlist_changed();
+    }
+
+    fn insert_item(&mut self, index: usize, item: PlaylistItem) -> usize {
+        let id = self.playlist.insert(index, item);
+        let index = self.playlist.position_of(id).unwrap();
+        let signal = TrackListSignal::TrackAdded {
+            metadata: track_metadata(&self.playlist.items()[index]),
+            after_track: index
+                .checked_sub(1)
+                .map_or(TrackId::NO_TRACK, |previous| track_id(&self.playlist.items()[previous])),
+        };
+        glib::idle_add_local_once(move || emit_mpris_track_list_signal(signal));
+        index
+    }
+
+    fn track_list_replaced(&self) {
+        let signal = TrackListSignal::TrackListReplaced {
+            tracks: self.playlist.items().iter().map(track_id).collect(),
+            current_track: self.current_item().map_or(TrackId::NO_TRACK, track_id),
+        };
+        glib::idle_add_local_once(move || emit_mpris_track_list_signal(signal));
+    }
+
+    fn playlist_changed(&self) {
+        self.notify(PlayerEvent::PlaylistChanged);
         self.update_mpris_nav_controls();
-        let uri = &*self.playlist[self.index];
+        self.update_gapless_next_item();
+    }
+
+    fn load_current_item(&mut self, player: &gst_play::Play, previous_index: usize) {
+        let Some(uri) = self.playlist.current().map(|item| item.uri.clone()) else {
+            return;
+        };
+        if self.playlist.index() == previous_index {
+            // Repeating the same item, no need to reload it. After EOS, GstPlay restarts from the beginning.
+            player.seek(gst::ClockTime::ZERO);
+            player.play();
+        } else {
+            self.load_uri(player, &uri);
+        }
+    }
+
+    fn load_uri(&mut self, player: &gst_play::Play, uri: &str) {
+        self.finish_crossfade(player);
+        self.gapless_uri = None;
+        self.gapless.lock().unwrap().queued = None;
+        self.queued_chapters = None;
+        self.set_chapters(vec![]);
+        self.set_ab_repeat(AbRepeat::default());
         player.set_property("uri", uri);
-        true
     }
 
-    fn playlist_length(&self) -> usize {
-        self.playlist.len()
+    // Tables of contents posted once the next item is queued belong to it, not to the one still playing.
+    // The first one found for a media is kept, unless it is updated.
+    fn toc_received(&mut self, chapters: Vec<Chapter>, updated: bool, queued: bool) {
+        if queued {
+            self.queued_chapters = Some(chapters);
+        } else if updated || self.chapters.is_empty() {
+            self.set_chapters(chapters);
+        }
+    }
+
+    // Apply what is remembered about the media GstPlay just loaded.
+    fn uri_loaded(&mut self, player: &gst_play::Play, uri: &str) {
+        // Tracks faded in start from the beginning.
+        if self.crossfade.is_none() {
+            if let Some(record) = self.cache.as_ref().and_then(|c| c.record(uri)) {
+                if let Some(offset) = record.audio_video_offset {
+                    player.set_audio_video_offset(offset);
+                }
+                if let Some(offset) = record.subtitle_video_offset {
+                    player.set_subtitle_video_offset(offset);
+                }
+                if let Some(rate) = record.rate {
+                    player.set_rate(rate);
+                }
+            }
+        }
+        let resume_position = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.record(uri))
+            .and_then(|record| record.resume_position())
+            .filter(|_| self.crossfade.is_none());
+        self.pending_resume = None;
+        match (resume_position, self.resume_mode) {
+            (Some(position), ResumeMode::Always) => player.seek(position),
+            (Some(position), ResumeMode::Ask) => self.pending_resume = Some(position),
+            _ => {}
+        }
+        self.notify(PlayerEvent::ResumeAvailable(self.pending_resume));
+    }
+
+    fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+        if self.chapters != chapters {
+            self.chapters = chapters;
+            self.notify(PlayerEvent::ChaptersChanged);
+        }
+    }
+
+    // URI of the media being played, which GstPlay doesn't know about after a gapless transition.
+    fn playing_uri(&self, player: &gst_play::Play) -> Option<glib::GString> {
+        self.gapless_uri.clone().or_else(|| player.uri())
+    }
+
+    fn update_gapless_next_item(&self) {
+        // Crossfading takes over the transitions between tracks.
+        let next = if self.crossfade_duration.is_zero() {
+            self.playlist
+                .upcoming()
+                .filter(|item| media_directory::is_audio(&item.uri))
+                .map(|item| (item.id, item.uri.clone()))
+        } else {
+            None
+        };
+        self.gapless.lock().unwrap().next = next;
+    }
+
+    fn position_updated(&mut self, player: &gst_play::Play) {
+        if self.crossfade.as_ref().is_none_or(|crossfade| crossfade.dominant) {
+            self.notify(PlayerEvent::PositionUpdated);
+        }
+        if self.crossfade.is_none() {
+            self.maybe_start_crossfade(player);
+        }
+    }
+
+    fn set_crossfade_duration(&mut self, duration: gst::ClockTime) {
+        self.crossfade_duration = duration;
+        self.update_gapless_next_item();
+    }
+
+    // Start playing the end of the current track in a separate pipeline once it reaches the crossfade window.
+    // The main player switches to the next track when that pipeline is ready. A playbin only ever plays one
+    // URI at a time, even the gapless switch replaces the ending stream instead of mixing it with the next
+    // one, so overlapping tracks need a second, audio-only, playbin.
+    fn maybe_start_crossfade(&mut self, player: &gst_play::Play) {
+        let fade = self.crossfade_duration;
+        if fade.is_zero() || !matches!(self.state, PlaybackState::Playing) || !self.gapless.lock().unwrap().enabled {
+            return;
+        }
+        let (Some(position), Some(duration)) = (player.position(), player.duration()) else {
+            return;
+        };
+        // The fade lasts the same wall-clock time at any speed, which covers more of the media when it's faster.
+        let rate = self.playback_rate(player);
+        let window = gst::ClockTime::from_nseconds((fade.nseconds() as f64 * rate) as u64);
+        // Short clips would be faded out entirely.
+        if duration < fade * 2
+            || position + window < duration
+            || !self
+                .playlist
+                .upcoming()
+                .is_some_and(|item| media_directory::is_audio(&item.uri))
+        {
+            return;
+        }
+        let Some(uri) = self.playing_uri(player) else {
+            return;
+        };
+
+        let outgoing = match gst::ElementFactory::make("playbin")
+            .property("uri", uri.as_str())
+            .build()
+        {
+            Ok(element) => element,
+            Err(e) => {
+                eprintln!("Unable to crossfade: {e}");
+                return;
+            }
+        };
+        outgoing.set_property_from_str("flags", "audio+soft-volume");
+        outgoing.set_property("volume", player.volume());
+
+        let Some(bus_watch) = outgoing.bus().and_then(|bus| {
+            bus.add_watch_local(clone!(
+                #[weak]
+                player,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move |_, message| {
+                    with_mut_player!(player player_data {
+                        player_data.crossfade_message(&player, message);
+                    });
+                    glib::ControlFlow::Continue
+                }
+            ))
+            .ok()
+        }) else {
+            return;
+        };
+
+        if outgoing.set_state(gst::State::Paused).is_err() {
+            let _ = outgoing.set_state(gst::State::Null);
+            return;
+        }
+        self.crossfade = Some(Crossfade {
+            outgoing,
+            bus_watch,
+            volume: player.volume(),
+            started: None,
+            timeout: None,
+            dominant: false,
+        });
+    }
+
+    fn crossfade_message(&mut self, player: &gst_play::Play, message: &gst::Message) {
+        let rate = self.playback_rate(player);
+        let Some(ref mut crossfade) = self.crossfade else {
+            return;
+        };
+        match message.view() {
+            gst::MessageView::AsyncDone(_) if crossfade.started.is_none() => {
+                // Catch up with the main player, at its speed, then hand it the next track.
+                let position = player.position().unwrap_or(gst::ClockTime::ZERO);
+                seek_crossfade(&crossfade.outgoing, rate, position);
+                let _ = crossfade.outgoing.set_state(gst::State::Playing);
+                crossfade.started = Some(std::time::Instant::now());
+                self.crossfade_handoff(player);
+            }
+            gst::MessageView::Eos(_) => {
+                let _ = crossfade.outgoing.set_state(gst::State::Null);
+            }
+            gst::MessageView::Error(e) => {
+                eprintln!("Crossfade error: {}", e.error());
+                if crossfade.started.is_some() {
+                    let _ = crossfade.outgoing.set_state(gst::State::Null);
+                } else {
+                    self.finish_crossfade(player);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn crossfade_handoff(&mut self, player: &gst_play::Play) {
+        if self.playlist.advance().is_none() {
+            self.finish_crossfade(player);
+            return;
+        }
+        let Some(uri) = self.current_item().map(|item| item.uri.clone()) else {
+            return;
+        };
+        self.playlist_changed();
+        self.fade_volume.set_property("volume", 0.0);
+        self.gapless_uri = None;
+        self.gapless.lock().unwrap().queued = None;
+        self.queued_chapters = None;
+        self.set_chapters(vec![]);
+        self.set_ab_repeat(AbRepeat::default());
+        player.set_property("uri", &uri);
+
+        let timeout = glib::timeout_add_local(
+            std::time::Duration::from_millis(50),
+            clone!(
+                #[weak]
+                player,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    let mut result = glib::ControlFlow::Break;
+                    with_mut_player!(player player_data {
+                        result = player_data.crossfade_step(&player);
+                    });
+                    result
+                }
+            ),
+        );
+        if let Some(ref mut crossfade) = self.crossfade {
+            crossfade.timeout = Some(timeout);
+        }
+    }
+
+    fn crossfade_step(&mut self, player: &gst_play::Play) -> glib::ControlFlow {
+        let Some(ref mut crossfade) = self.crossfade else {
+            return glib::ControlFlow::Break;
+        };
+        let Some(started) = crossfade.started else {
+            return glib::ControlFlow::Continue;
+        };
+        let progress = (started.elapsed().as_secs_f64() / self.crossfade_duration.seconds_f64()).min(1.0);
+
+        // Equal power curves, so that the overall loudness stays constant.
+        let angle = progress * std::f64::consts::FRAC_PI_2;
+        self.fade_volume.set_property("volume", angle.sin());
+        crossfade
+            .outgoing
+            .set_property("volume", crossfade.volume * angle.cos());
+
+        if progress >= 1.0 {
+            crossfade.timeout = None;
+            self.finish_crossfade(player);
+            return glib::ControlFlow::Break;
+        }
+        if progress >= 0.5 && !crossfade.dominant {
+            crossfade.dominant = true;
+            self.announce_current_media(player);
+        }
+        glib::ControlFlow::Continue
+    }
+
+    // Stop the outgoing track, if any, and restore the main player volume.
+    fn finish_crossfade(&mut self, player: &gst_play::Play) {
+        let Some(mut crossfade) = self.crossfade.take() else {
+            return;
+        };
+        let _ = crossfade.outgoing.set_state(gst::State::Null);
+        if let Some(timeout) = crossfade.timeout.take() {
+            timeout.remove();
+        }
+        self.fade_volume.set_property("volume", 1.0);
+        if crossfade.started.is_some() && !crossfade.dominant {
+            self.announce_current_media(player);
+        }
+    }
+
+    // Media informations of the incoming track were held back during the first half of the crossfade.
+    fn announce_current_media(&mut self, player: &gst_play::Play) {
+        self.duration_changed(player.duration());
+        if let Some(info) = player.media_info() {
+            self.media_info_updated(&info);
+        }
+    }
+
+    // The pipeline started playing the item queued when the previous one was about to finish.
+    fn gapless_transition(&mut self, player: &gst_play::Play) {
+        let Some((id, uri)) = self.gapless.lock().unwrap().queued.take() else {
+            return;
+        };
+        if let Some(previous_uri) = self.playing_uri(player) {
+            self.notify(PlayerEvent::EndOfStream(previous_uri.into()));
+        }
+        if let Some(index) = self.playlist.position_of(id) {
+            self.playlist.jump(index);
+        }
+        with_fingerprint(player, &uri, || {});
+        self.gapless_uri = Some(uri.into());
+        self.set_ab_repeat(AbRepeat::default());
+        let chapters = self.queued_chapters.take().unwrap_or_default();
+        self.set_chapters(chapters);
+
+        // Handle the next media informations like those of a newly loaded asset.
+        self.current_uri = "".into();
+        self.playlist_changed();
+
+        let Some(item) = self.current_item() else {
+            return;
+        };
+        let metadata = track_metadata(item);
+        let duration = player.pipeline().query_duration::<gst::ClockTime>().or(item.duration);
+        self.metadata.replace(metadata.clone());
+        mpris_properties_changed([Property::Metadata(metadata)]);
+        self.duration_changed(duration);
+    }
+
+    fn current_item(&self) -> Option<&PlaylistItem> {
+        self.playlist.current()
     }
 
     fn can_go_next(&self) -> bool {
-        self.index < self.playlist_length() - 1
+        self.playlist.can_go_next()
     }
 
     fn can_go_prev(&self) -> bool {
-        self.index >= 1
+        self.playlist.can_go_prev()
+    }
+
+    fn loop_mode(&self) -> LoopMode {
+        self.playlist.loop_mode()
+    }
+
+    fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        if self.playlist.loop_mode() == loop_mode {
+            return;
+        }
+        self.playlist.set_loop_mode(loop_mode);
+        self.notify(PlayerEvent::LoopModeChanged(loop_mode));
+        self.update_gapless_next_item();
+        glib::idle_add_local_once(move || {
+            mpris_properties_changed([Property::LoopStatus(loop_mode.into())]);
+        });
+        self.update_mpris_nav_controls();
     }
 
     fn can_seek(&self) -> bool {
@@ -429,10 +1173,64 @@ impl PlayerDataHolder {
         });
     }
 
-    fn update_cache_and_write(&mut self, id: string::String, position: u64) {
-        if let Some(ref mut cache) = self.cache {
-            cache.update(id, position);
-            cache.write().unwrap();
+    fn is_shuffled(&self) -> bool {
+        self.playlist.is_shuffled()
+    }
+
+    fn set_shuffle(&mut self, enabled: bool) {
+        if self.playlist.is_shuffled() == enabled {
+            return;
+        }
+        self.playlist.set_shuffle(enabled);
+        self.notify(PlayerEvent::ShuffleChanged(enabled));
+        self.update_gapless_next_item();
+        glib::idle_add_local_once(move || {
+            mpris_properties_changed([Property::Shuffle(enabled)]);
+        });
+        self.update_mpris_nav_controls();
+    }
+
+    fn update_cache<F: FnOnce(&mut MediaCache)>(&mut self, player: &gst_play::Play, f: F) {
+        let Some(ref mut cache) = self.cache else {
+            return;
+        };
+        f(cache);
+        if self.cache_write_source.is_some() {
+            return;
+        }
+        let source = glib::timeout_add_seconds_local_once(
+            CACHE_WRITE_DELAY_SECONDS,
+            clone!(
+                #[weak]
+                player,
+                move || {
+                    with_mut_player!(player player_data {
+                        player_data.cache_write_source = None;
+                        player_data.write_cache();
+                    });
+                }
+            ),
+        );
+        self.cache_write_source = Some(source);
+    }
+
+    fn write_cache(&mut self) {
+        if let Some(source) = self.cache_write_source.take() {
+            source.remove();
+        }
+        let Some(ref mut cache) = self.cache else {
+            return;
+        };
+        match cache.write() {
+            Ok(()) => self.cache_write_failed = false,
+            Err(e) => {
+                eprintln!("Unable to write media cache: {e}");
+                // Report the first failure only, until writing works again.
+                if !self.cache_write_failed {
+                    self.cache_write_failed = true;
+                    self.notify(PlayerEvent::CacheWriteFailed(e.to_string()));
+                }
+            }
         }
     }
 }
@@ -497,16 +1295,35 @@ impl ChannelPlayer {
 
                 match play_message {
                     PlayMessage::UriLoaded(_) => {
-                        player.pause();
                         let uri = player.uri().unwrap();
-                        with_mut_player!(player player_data {
-                            if let Some(ref cache) = player_data.cache {
-                                if let Some(position) = cache.find_last_position(&uri) {
-                                    player.seek(position);
-                                }
-                            }
+                        let mut known = false;
+                        with_player!(player player_data {
+                            known = player_data.cache.as_ref().is_some_and(|cache| cache.record(&uri).is_some());
                         });
-                        player.play();
+                        if known {
+                            start_loaded_media(&player, &uri);
+                            // Only stored in the record, in case the file is moved later on.
+                            with_fingerprint(&player, &uri, || {});
+                        } else {
+                            // The record of a file moved since it was played is found from its fingerprint,
+                            // it is needed before the media starts.
+                            with_fingerprint(
+                                &player,
+                                &uri,
+                                clone!(
+                                    #[weak]
+                                    player,
+                                    #[strong]
+                                    uri,
+                                    move || {
+                                        // Another media might have been loaded meanwhile.
+                                        if player.uri().as_ref() == Some(&uri) {
+                                            start_loaded_media(&player, &uri);
+                                        }
+                                    }
+                                ),
+                            );
+                        }
                     }
                     PlayMessage::EndOfStream(_) => {
                         with_mut_player!(player player_data {
@@ -524,8 +1341,8 @@ impl ChannelPlayer {
                         });
                     }
                     PlayMessage::PositionUpdated(_) => {
-                        with_player!(player {
-                            player.notify(PlayerEvent::PositionUpdated);
+                        with_mut_player!(player player_data {
+                            player_data.position_updated(&player);
                         });
                     }
                     PlayMessage::VideoDimensionsChanged(message) => {
@@ -573,80 +1390,348 @@ impl ChannelPlayer {
             }
         ))?;
 
+        // Applied to the main pipeline only, so that fading doesn't affect the volume set by the user.
+        let fade_volume = gst::ElementFactory::make("volume").build()?;
+        player.pipeline().set_property("audio-filter", &fade_volume);
+
+        // Chain the next playlist item before the current one ends, so that audio tracks follow each other
+        // without silence.
+        let gapless = Arc::new(Mutex::new(GaplessState::default()));
+        let pipeline = player.pipeline();
+        let state = gapless.clone();
+        pipeline.connect("about-to-finish", false, move |args| {
+            let playbin = args[0].get::<gst::Element>().unwrap();
+            let mut state = state.lock().unwrap();
+            if state.enabled {
+                if let Some((id, uri)) = state.next.clone() {
+                    playbin.set_property("uri", &uri);
+                    state.queued = Some((id, uri));
+                }
+            }
+            None
+        });
+
+        // The new stream starting is the actual track boundary.
+        let state = gapless.clone();
+        if let Some(bus) = pipeline.bus() {
+            bus.connect_message(
+                Some("stream-start"),
+                clone!(
+                    #[weak]
+                    player,
+                    move |_, _| {
+                        if state.lock().unwrap().queued.is_none() {
+                            return;
+                        }
+                        glib::MainContext::default().invoke(move || {
+                            with_mut_player!(player player_data {
+                                player_data.gapless_transition(&player);
+                            });
+                        });
+                    }
+                ),
+            );
+        }
+
+        // GstPlay doesn't expose the table of contents of the media, demuxers post it on the pipeline bus.
+        let state = gapless.clone();
+        if let Some(bus) = pipeline.bus() {
+            bus.connect_message(
+                Some("toc"),
+                clone!(
+                    #[weak]
+                    player,
+                    move |_, message| {
+                        let gst::MessageView::Toc(message) = message.view() else {
+                            return;
+                        };
+                        let (toc, updated) = message.toc();
+                        // Tables scoped to the current stream are meant for muxers, the global one lists all chapters.
+                        if toc.scope() != gst::TocScope::Global {
+                            return;
+                        }
+                        let chapters = chapters::from_toc(&toc);
+                        let queued = state.lock().unwrap().queued.is_some();
+                        glib::MainContext::default().invoke(move || {
+                            with_mut_player!(player player_data {
+                                player_data.toc_received(chapters, updated, queued);
+                            });
+                        });
+                    }
+                ),
+            );
+        }
+
+        let subtitle_style = Arc::new(Mutex::new(SubtitleStyle::default()));
+        let style = subtitle_style.clone();
+        if let Some(bin) = pipeline.downcast_ref::<gst::Bin>() {
+            bin.connect_deep_element_added(move |_, _, element| {
+                if element.factory().is_some_and(|factory| factory.name() == "textoverlay") {
+                    apply_subtitle_style(element, &style.lock().unwrap());
+                }
+            });
+        }
+
+        let ab_repeat = Arc::new(Mutex::new(AbRepeat::default()));
+        let state = ab_repeat.clone();
+        if let Some(bus) = pipeline.bus() {
+            bus.connect_message(
+                Some("segment-done"),
+                clone!(
+                    #[weak]
+                    player,
+                    move |_, _| {
+                        let ab_repeat = *state.lock().unwrap();
+                        if let Some((a, b)) = ab_repeat.segment() {
+                            let rate = ab_repeat.rate.unwrap_or_else(|| player.rate());
+                            seek_segment(&player, rate, a, b, false);
+                        }
+                    }
+                ),
+            );
+        }
+
         player.connect_audio_video_offset_notify(|player| {
             with_player!(player player_data {
                 player_data.notify(PlayerEvent::AudioVideoOffsetChanged(player.audio_video_offset()));
             });
         });
 
-        player.connect_subtitle_video_offset_notify(|player| {
-            with_player!(player player_data {
-                player_data.notify(PlayerEvent::SubtitleVideoOffsetChanged(player.subtitle_video_offset()));
-            });
+        player.connect_subtitle_video_offset_notify(|player| {
+            with_player!(player player_data {
+                player_data.notify(PlayerEvent::SubtitleVideoOffsetChanged(player.subtitle_video_offset()));
+            });
+        });
+
+        let player_id = player.name();
+        let subscribers = vec![sender];
+        let mut cache = None;
+        if !incognito {
+            if let Some(ref path) = cache_dir_path {
+                let cache_path = path.join("media-cache.json");
+                let mut media_cache = MediaCache::open(cache_path);
+                media_cache.evict_stale();
+                cache = Some(media_cache);
+            }
+        }
+        let player_data = PlayerDataHolder {
+            subscribers,
+            playlist: Playlist::default(),
+            current_uri: "".into(),
+            gapless,
+            gapless_uri: None,
+            crossfade_duration: gst::ClockTime::ZERO,
+            crossfade: None,
+            fade_volume,
+            cache,
+            cache_write_source: None,
+            cache_write_failed: false,
+            resume_mode: ResumeMode::default(),
+            pending_resume: None,
+            chapters: vec![],
+            queued_chapters: None,
+            ab_repeat,
+            subtitle_style,
+            bus_watch,
+            state: PlaybackState::Stopped,
+            metadata: RefCell::new(Metadata::new()),
+            seekable: false,
+        };
+
+        PLAYER_REGISTRY.with(move |registry| {
+            registry.borrow_mut().insert(player_id, player_data);
+        });
+
+        let result = Self {
+            player,
+            renderer,
+            gtksink,
+            cache_dir_path: cache_dir_path.map(|d| d.to_path_buf()),
+            gtk_app,
+        };
+
+        if !incognito {
+            let player = result.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let local_server = LocalServer::new_with_track_list(config::app_id(), player)
+                    .await
+                    .expect("Unable to create MPRIS server");
+                glib::MainContext::default().spawn_local(local_server.run());
+                MPRIS_SERVER.with(|mut server| {
+                    let _ = server.borrow_mut().set_blocking(local_server);
+                });
+            });
+        }
+        Ok(result)
+    }
+
+    #[allow(dead_code)]
+    pub fn register_event_handler(&mut self, sender: async_channel::Sender<PlayerEvent>) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.register_event_handler(sender);
+        });
+    }
+
+    pub fn load_playlist(&self, playlist: Vec<PlaylistItem>) {
+        if playlist.is_empty() {
+            return;
+        }
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            // When shuffling, the first item to play is picked randomly.
+            player_data.set_playlist(playlist);
+            if let Some(uri) = player_data.current_item().map(|item| item.uri.clone()) {
+                player_data.load_uri(player, &uri);
+            }
+        });
+    }
+
+    pub fn enqueue(&self, items: Vec<PlaylistItem>, play_next: bool) {
+        let mut is_empty = true;
+        let player = &self.player;
+        with_player!(player player_data {
+            is_empty = player_data.playlist.is_empty();
+        });
+        if is_empty {
+            self.load_playlist(items);
+            return;
+        }
+        with_mut_player!(player player_data {
+            player_data.enqueue(items, play_next);
+        });
+    }
+
+    pub fn get_playlist(&self) -> (Vec<PlaylistItem>, usize) {
+        let mut result = (vec![], 0);
+        let player = &self.player;
+        with_player!(player player_data {
+            result = (player_data.playlist.items().to_vec(), player_data.playlist.index());
+        });
+        result
+    }
+
+    pub fn jump_to(&self, index: usize) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.jump_to(player, index);
+        });
+    }
+
+    pub fn remove_from_playlist(&self, index: usize) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.remove_item(player, index);
+        });
+    }
+
+    pub fn move_in_playlist(&self, from: usize, to: usize) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.move_item(from, to);
+        });
+    }
+
+    pub fn play_next(&self, index: usize) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.play_next(index);
+        });
+    }
+
+    pub fn loop_mode(&self) -> LoopMode {
+        let mut result = LoopMode::None;
+        let player = &self.player;
+        with_player!(player player_data {
+            result = player_data.loop_mode();
+        });
+        result
+    }
+
+    pub fn set_loop_mode(&self, loop_mode: LoopMode) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.set_loop_mode(loop_mode);
+        });
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        let mut result = false;
+        let player = &self.player;
+        with_player!(player player_data {
+            result = player_data.is_shuffled();
+        });
+        result
+    }
+
+    pub fn set_shuffle(&self, enabled: bool) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.set_shuffle(enabled);
         });
+    }
 
-        let player_id = player.name();
-        let subscribers = vec![sender];
-        let mut cache = None;
-        if !incognito {
-            if let Some(ref path) = cache_dir_path {
-                let cache_path = path.join("media-cache.json");
-                cache = Some(MediaCache::open(&cache_path).unwrap());
+    pub fn set_shuffle_seed(&self, seed: u64) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.playlist.set_seed(seed);
+            if player_data.playlist.is_shuffled() {
+                player_data.update_gapless_next_item();
+                player_data.update_mpris_nav_controls();
             }
-        }
-        let player_data = PlayerDataHolder {
-            subscribers,
-            playlist: vec![],
-            current_uri: "".into(),
-            index: 0,
-            cache,
-            bus_watch,
-            state: PlaybackState::Stopped,
-            metadata: RefCell::new(Metadata::new()),
-            seekable: false,
-        };
-
-        PLAYER_REGISTRY.with(move |registry| {
-            registry.borrow_mut().insert(player_id, player_data);
         });
+    }
 
-        let result = Self {
-            player,
-            renderer,
-            gtksink,
-            cache_dir_path: cache_dir_path.map(|d| d.to_path_buf()),
-            gtk_app,
-        };
+    pub fn save_playlist(&self, path: &path::Path) -> anyhow::Result<()> {
+        let mut playlist = vec![];
+        let player = &self.player;
+        with_player!(player player_data {
+            playlist = player_data.playlist.items().to_vec();
+        });
+        playlist_format::save(path, &playlist)
+    }
 
-        if !incognito {
-            let player = result.clone();
-            glib::MainContext::default().spawn_local(async move {
-                let local_server = LocalServer::new(config::app_id(), player)
-                    .await
-                    .expect("Unable to create MPRIS server");
-                glib::MainContext::default().spawn_local(local_server.run());
-                MPRIS_SERVER.with(|mut server| {
-                    let _ = server.borrow_mut().set_blocking(local_server);
-                });
-            });
+    pub fn session(&self) -> Option<Session> {
+        let (playlist, index) = self.get_playlist();
+        if playlist.is_empty() {
+            return None;
         }
-        Ok(result)
+        Some(Session {
+            playlist,
+            index,
+            volume: self.player.volume(),
+            rate: self.playback_rate(),
+        })
     }
 
-    #[allow(dead_code)]
-    pub fn register_event_handler(&mut self, sender: async_channel::Sender<PlayerEvent>) {
+    /// The media played last, most recent first. Always empty in incognito mode.
+    pub fn recent_media(&self, limit: usize) -> Vec<MediaRecord> {
+        let mut records = vec![];
         let player = &self.player;
-        with_mut_player!(player player_data {
-            player_data.register_event_handler(sender);
+        with_player!(player player_data {
+            if let Some(cache) = player_data.cache.as_ref() {
+                records = cache.recent(limit).into_iter().cloned().collect();
+            }
         });
+        records
     }
 
-    pub fn load_playlist(&self, playlist: Vec<string::String>) {
-        assert!(!playlist.is_empty());
+    pub fn restore_session(&self, session: Session) {
+        if session.playlist.is_empty() {
+            return;
+        }
+        self.player.set_volume(session.volume.clamp(0.0, 1.0));
+        self.player.set_rate(session.rate.clamp(0.25, 2.0));
         let player = &self.player;
         with_mut_player!(player player_data {
-            self.load_uri(&playlist[0]);
-            player_data.set_playlist(playlist);
+            // MPRIS clients are told about the current item along with the new track list.
+            player_data.playlist.set_items(session.playlist);
+            player_data.playlist.jump(session.index);
+            player_data.playlist_changed();
+            player_data.track_list_replaced();
+            if let Some(uri) = player_data.current_item().map(|item| item.uri.clone()) {
+                player_data.load_uri(player, &uri);
+            }
         });
     }
 
@@ -670,18 +1755,43 @@ impl ChannelPlayer {
         }
     }
 
-    pub fn load_uri(&self, uri: &str) {
-        self.player.set_property("uri", uri);
+    pub fn get_current_item(&self) -> Option<PlaylistItem> {
+        let mut result = None;
+        let player = &self.player;
+        with_player!(player player_data {
+            result = player_data.current_item().cloned();
+        });
+        result
     }
 
     pub fn get_current_uri(&self) -> Option<glib::GString> {
-        self.player.uri()
+        let mut result = None;
+        let player = &self.player;
+        with_player!(player player_data {
+            result = player_data.playing_uri(player);
+        });
+        result
     }
 
     pub fn stop(&self) {
+        self.finish_crossfade();
         self.player.stop();
     }
 
+    fn finish_crossfade(&self) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.finish_crossfade(player);
+        });
+    }
+
+    pub fn set_crossfade_duration(&self, duration: gst::ClockTime) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.set_crossfade_duration(duration);
+        });
+    }
+
     pub fn get_media_info(&self) -> Option<gst_play::PlayMediaInfo> {
         self.player.media_info()
     }
@@ -698,6 +1808,7 @@ impl ChannelPlayer {
         if currently_paused {
             self.player.play();
         } else {
+            self.finish_crossfade();
             self.player.pause();
         }
     }
@@ -745,11 +1856,14 @@ impl ChannelPlayer {
             _ => return,
         };
 
-        self.player.seek(destination);
+        self.seek_to(destination);
     }
 
     pub fn seek_to(&self, position: gst::ClockTime) {
-        self.player.seek(position);
+        let player = &self.player;
+        with_player!(player player_data {
+            player_data.seek(player, position);
+        });
     }
 
     pub fn get_position(&self) -> Option<gst::ClockTime> {
@@ -757,6 +1871,19 @@ impl ChannelPlayer {
     }
 
     pub fn configure_subtitle_track(&self, track: Option<SubtitleTrack>) {
+        let (subtitle_track, subtitle_uri) = match track {
+            Some(SubtitleTrack::External(ref uri)) => (None, Some(uri.to_string())),
+            Some(SubtitleTrack::Inband(idx)) => (Some(idx), None),
+            None => (Some(-1), None),
+        };
+        self.apply_subtitle_track(track);
+        self.remember(|record| {
+            record.subtitle_track = subtitle_track;
+            record.subtitle_uri = subtitle_uri;
+        });
+    }
+
+    fn apply_subtitle_track(&self, track: Option<SubtitleTrack>) {
         let enabled = if let Some(track) = track {
             match track {
                 SubtitleTrack::External(uri) => {
@@ -773,6 +1900,110 @@ impl ChannelPlayer {
         self.player.set_subtitle_track_enabled(enabled);
     }
 
+    // Store a choice made for the current media, so that it applies again when the media is played next.
+    fn remember<F: FnOnce(&mut MediaRecord)>(&self, f: F) {
+        if let Some(uri) = self.get_current_uri() {
+            self.remember_uri(&uri, f);
+        }
+    }
+
+    fn remember_uri<F: FnOnce(&mut MediaRecord)>(&self, uri: &str, f: F) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.update_cache(player, |cache| cache.update(uri, f));
+        });
+    }
+
+    /// Chapters of the current media, sorted by start time.
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let mut chapters = vec![];
+        let player = &self.player;
+        with_player!(player player_data {
+            chapters = player_data.chapters.clone();
+        });
+        chapters
+    }
+
+    /// Bookmarks of the current media, sorted by position.
+    pub fn bookmarks(&self) -> Vec<Bookmark> {
+        match self.get_current_uri() {
+            Some(uri) => self.bookmarks_of(&uri),
+            None => vec![],
+        }
+    }
+
+    /// Bookmarks of the media at `uri`, sorted by position.
+    pub fn bookmarks_of(&self, uri: &str) -> Vec<Bookmark> {
+        let mut bookmarks = vec![];
+        let player = &self.player;
+        with_player!(player player_data {
+            if let Some(record) = player_data.cache.as_ref().and_then(|cache| cache.record(uri)) {
+                bookmarks = record.bookmarks.clone();
+            }
+        });
+        bookmarks
+    }
+
+    /// Bookmark a moment of the media at `uri`, which might not be the current one anymore once the user
+    /// picked a label.
+    pub fn add_bookmark(&self, uri: &str, position: gst::ClockTime, label: Option<string::String>) {
+        self.remember_uri(uri, |record| {
+            bookmarks::insert(&mut record.bookmarks, Bookmark::new(position, label));
+        });
+    }
+
+    pub fn rename_bookmark(&self, uri: &str, bookmark: &Bookmark, label: Option<string::String>) {
+        self.remember_uri(uri, |record| {
+            if let Some(bookmark) = record.bookmarks.iter_mut().find(|b| *b == bookmark) {
+                bookmark.label = label;
+            }
+        });
+    }
+
+    pub fn remove_bookmark(&self, uri: &str, position: gst::ClockTime) {
+        self.remember_uri(uri, |record| {
+            if let Some(index) = record.bookmarks.iter().position(|b| b.position == position) {
+                record.bookmarks.remove(index);
+            }
+        });
+    }
+
+    /// Select again the tracks last chosen for the current media. Returns the record they come from.
+    pub fn restore_track_selection(&self, info: &gst_play::PlayMediaInfo) -> Option<MediaRecord> {
+        let uri = self.get_current_uri()?;
+        let mut record = None;
+        let player = &self.player;
+        with_player!(player player_data {
+            record = player_data.cache.as_ref().and_then(|cache| cache.record(&uri)).cloned();
+        });
+        let record = record?;
+
+        if let Some(idx) = record.audio_track {
+            if idx < info.number_of_audio_streams() as i32 {
+                self.apply_audio_track_index(idx);
+            }
+        }
+        if let Some(idx) = record.video_track {
+            if idx < info.number_of_video_streams() as i32 {
+                self.apply_video_track_index(idx);
+            }
+        }
+        let external_subtitle = record.subtitle_uri.as_ref().filter(|uri| {
+            // The subtitle file might have been moved away since.
+            glib::filename_from_uri(uri).map_or(true, |(path, _)| path.is_file())
+        });
+        if let Some(uri) = external_subtitle {
+            self.apply_subtitle_track(Some(SubtitleTrack::External(uri.into())));
+        } else if let Some(idx) = record.subtitle_track {
+            if idx < 0 {
+                self.apply_subtitle_track(None);
+            } else if idx < info.number_of_subtitle_streams() as i32 {
+                self.apply_subtitle_track(Some(SubtitleTrack::Inband(idx)));
+            }
+        }
+        Some(record)
+    }
+
     pub fn get_current_subtitle_track(&self) -> Option<gst_play::PlaySubtitleInfo> {
         self.player.current_subtitle_track()
     }
@@ -782,6 +2013,11 @@ impl ChannelPlayer {
     }
 
     pub fn set_audio_track_index(&self, idx: i32) {
+        self.apply_audio_track_index(idx);
+        self.remember(|record| record.audio_track = Some(idx));
+    }
+
+    fn apply_audio_track_index(&self, idx: i32) {
         self.player.set_audio_track_enabled(idx > -1);
         if idx >= 0 {
             self.player.set_audio_track(idx).unwrap();
@@ -789,6 +2025,11 @@ impl ChannelPlayer {
     }
 
     pub fn set_video_track_index(&self, idx: i32) {
+        self.apply_video_track_index(idx);
+        self.remember(|record| record.video_track = Some(idx));
+    }
+
+    fn apply_video_track_index(&self, idx: i32) {
         self.player.set_video_track_enabled(idx > -1);
         if idx >= 0 {
             self.player.set_video_track(idx).unwrap();
@@ -815,40 +2056,85 @@ impl ChannelPlayer {
     }
 
     pub fn write_last_known_media_position(&self) {
-        if let Some(uri) = self.player.uri() {
+        if let Some(uri) = self.get_current_uri() {
             if let Some(scheme) = glib::uri_parse_scheme(&uri) {
                 if scheme == "fd" {
                     return;
                 }
             }
-            let id = uri_to_sha256(&uri);
-            let mut position = 0;
-            if let Some(p) = self.player.position() {
-                position = p.nseconds();
-            }
-            if let Some(duration) = self.player.duration() {
-                if position == duration.nseconds() {
-                    return;
-                }
-            } else {
+            let position = self.player.position().unwrap_or(gst::ClockTime::ZERO);
+            let Some(duration) = self.player.duration() else {
                 // This likely is a live stream. Seek to last known
                 // position will likely fail.
                 return;
-            }
+            };
 
             let player = &self.player;
             with_mut_player!(player player_data {
-                player_data.update_cache_and_write(id, position);
+                player_data.update_cache(player, |cache| {
+                    cache.update(&uri, |record| {
+                        record.position = Some(position);
+                        record.duration = Some(duration);
+                    });
+                });
             });
         }
     }
 
+    /// Write the pending changes to the media cache now, instead of with the next batch.
+    pub fn write_cache(&self) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.write_cache();
+        });
+    }
+
+    pub fn set_resume_mode(&self, mode: ResumeMode) {
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            player_data.resume_mode = mode;
+        });
+    }
+
+    /// Seek to the position offered by the last `ResumeAvailable` event, or forget about it.
+    pub fn answer_resume(&self, resume: bool) {
+        let mut position = None;
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            position = player_data.pending_resume.take();
+        });
+        if let Some(position) = position.filter(|_| resume) {
+            self.seek_to(position);
+        }
+    }
+
     pub fn set_audio_offset(&self, offset: i64) {
         self.player.set_property("audio-video-offset", offset);
+        self.remember(|record| record.audio_video_offset = Some(offset));
     }
 
     pub fn set_subtitle_offset(&self, offset: i64) {
         self.player.set_property("subtitle-video-offset", offset);
+        self.remember(|record| record.subtitle_video_offset = Some(offset));
+    }
+
+    /// Restyle the subtitles being shown, and the ones of the next media.
+    pub fn set_subtitle_style(&self, style: &SubtitleStyle) {
+        let player = &self.player;
+        with_player!(player player_data {
+            *player_data.subtitle_style.lock().unwrap() = style.clone();
+        });
+        let pipeline = self.player.pipeline();
+        pipeline.set_property("subtitle-font-desc", &style.font);
+        if let Some(bin) = pipeline.downcast_ref::<gst::Bin>() {
+            for overlay in bin
+                .iterate_all_by_element_factory_name("textoverlay")
+                .into_iter()
+                .flatten()
+            {
+                apply_subtitle_style(&overlay, style);
+            }
+        }
     }
 
     pub fn video_frame_step(&self) {
@@ -857,22 +2143,97 @@ impl ChannelPlayer {
     }
 
     pub fn playback_rate(&self) -> f64 {
-        self.player.rate()
+        let mut rate = self.player.rate();
+        let player = &self.player;
+        with_player!(player player_data {
+            rate = player_data.playback_rate(player);
+        });
+        rate
+    }
+
+    fn set_playback_rate(&self, rate: f64) {
+        let player = &self.player;
+        with_player!(player player_data {
+            player_data.set_playback_rate(player, rate);
+        });
+    }
+
+    /// Start the A–B repeat at the current position. The loop starts once its end is set too.
+    pub fn set_ab_repeat_start(&self) {
+        let Some(position) = self.player.position() else {
+            return;
+        };
+        let player = &self.player;
+        with_player!(player player_data {
+            let mut ab_repeat = *player_data.ab_repeat.lock().unwrap();
+            ab_repeat.a = Some(position);
+            if ab_repeat.b.is_some_and(|b| b <= position) {
+                ab_repeat.b = None;
+            }
+            player_data.set_ab_repeat(ab_repeat);
+            if let Some((_, b)) = ab_repeat.segment() {
+                seek_segment(player, player_data.playback_rate(player), position, b, true);
+            }
+        });
+    }
+
+    /// End the A–B repeat at the current position and go back to its start, the beginning of the media if
+    /// it wasn't set. Returns false if the position is before the start.
+    pub fn set_ab_repeat_end(&self) -> bool {
+        let Some(position) = self.player.position() else {
+            return false;
+        };
+        let mut result = false;
+        let player = &self.player;
+        with_player!(player player_data {
+            let mut ab_repeat = *player_data.ab_repeat.lock().unwrap();
+            let a = ab_repeat.a.unwrap_or(gst::ClockTime::ZERO);
+            if position > a {
+                ab_repeat.a = Some(a);
+                ab_repeat.b = Some(position);
+                player_data.set_ab_repeat(ab_repeat);
+                seek_segment(player, player_data.playback_rate(player), a, position, true);
+                result = true;
+            }
+        });
+        result
+    }
+
+    pub fn clear_ab_repeat(&self) {
+        let mut ab_repeat = AbRepeat::default();
+        let player = &self.player;
+        with_player!(player player_data {
+            ab_repeat = *player_data.ab_repeat.lock().unwrap();
+            player_data.set_ab_repeat(AbRepeat::default());
+        });
+        // Only a seek removes the end of the segment being played, changing the rate of GstPlay seeks already.
+        if ab_repeat.segment().is_some() {
+            match ab_repeat.rate {
+                Some(rate) => self.player.set_rate(rate),
+                None => {
+                    if let Some(position) = self.player.position() {
+                        self.player.seek(position);
+                    }
+                }
+            }
+        }
     }
 
     pub fn increase_speed(&self) {
-        let rate = self.player.rate();
+        let rate = self.playback_rate();
         let offset = 0.25;
         if rate + offset <= 2.0 {
-            self.player.set_rate(rate + offset);
+            self.set_playback_rate(rate + offset);
+            self.remember(|record| record.rate = Some(rate + offset));
         }
     }
 
     pub fn decrease_speed(&self) {
-        let rate = self.player.rate();
+        let rate = self.playback_rate();
         let offset = 0.25;
         if rate > offset {
-            self.player.set_rate(rate - offset);
+            self.set_playback_rate(rate - offset);
+            self.remember(|record| record.rate = Some(rate - offset));
         }
     }
 
@@ -886,8 +2247,8 @@ impl ChannelPlayer {
             .as_ref()
             .ok_or(anyhow::anyhow!("Unable to determine cache directory path."))?;
 
-        let uri = self.player.uri().unwrap();
-        let id = uri_to_sha256(&uri);
+        let uri = self.get_current_uri().unwrap();
+        let id = media_cache::uri_to_sha256(&uri);
 
         let tar_directory_name = format!("glide-error-{id}");
         let tar_filename = format!("{tar_directory_name}.tar");
@@ -1045,12 +2406,7 @@ impl LocalRootInterface for ChannelPlayer {
     }
 
     async fn has_track_list(&self) -> fdo::Result<bool> {
-        let mut result: Option<bool> = None;
-        let player_id = &self.player;
-        with_player!(player_id player_data {
-            result = Some(player_data.playlist_length() > 1);
-        });
-        result.ok_or(fdo::Error::Failed("Unable to determine result".to_string()))
+        Ok(true)
     }
 
     async fn identity(&self) -> fdo::Result<String> {
@@ -1062,11 +2418,11 @@ impl LocalRootInterface for ChannelPlayer {
     }
 
     async fn supported_uri_schemes(&self) -> fdo::Result<Vec<String>> {
-        Ok(vec![])
+        Ok(supported_uri_schemes())
     }
 
     async fn supported_mime_types(&self) -> fdo::Result<Vec<String>> {
-        Ok(vec![])
+        Ok(supported_mime_types())
     }
 }
 
@@ -1098,6 +2454,7 @@ impl LocalPlayerInterface for ChannelPlayer {
     }
 
     async fn pause(&self) -> fdo::Result<()> {
+        self.finish_crossfade();
         self.player.pause();
         Ok(())
     }
@@ -1109,7 +2466,7 @@ impl LocalPlayerInterface for ChannelPlayer {
     }
 
     async fn stop(&self) -> fdo::Result<()> {
-        self.player.stop();
+        ChannelPlayer::stop(self);
         Ok(())
     }
 
@@ -1129,12 +2486,47 @@ impl LocalPlayerInterface for ChannelPlayer {
         Ok(())
     }
 
-    async fn set_position(&self, _track_id: TrackId, _position: Time) -> fdo::Result<()> {
-        Err(fdo::Error::NotSupported("SetPosition is not supported".into()))
+    async fn set_position(&self, track_id: TrackId, position: Time) -> fdo::Result<()> {
+        let mut is_current = false;
+        let mut duration = None;
+        let player_id = &self.player;
+        with_player!(player_id player_data {
+            is_current = player_data.current_item().is_some_and(|item| self::track_id(item) == track_id);
+            duration = player_data.metadata.borrow().length();
+        });
+
+        // As per the specification, requests for a stale track or out of range positions are ignored.
+        if !is_current || position.is_negative() || duration.is_some_and(|duration| position > duration) {
+            return Ok(());
+        }
+        self.seek_to(gst::ClockTime::from_useconds(position.as_micros() as u64));
+        Ok(())
     }
 
-    async fn open_uri(&self, _uri: String) -> fdo::Result<()> {
-        Err(fdo::Error::NotSupported("OpenUri is not supported".into()))
+    async fn open_uri(&self, uri: String) -> fdo::Result<()> {
+        let scheme = glib::Uri::peek_scheme(&uri).ok_or(fdo::Error::InvalidArgs("Invalid URI".into()))?;
+        if !supported_uri_schemes().iter().any(|s| *s == scheme) {
+            return Err(fdo::Error::NotSupported(format!("Unsupported URI scheme: {scheme}")));
+        }
+
+        // Playlist files replace the current playlist, other media are appended to it and played right away.
+        let items = playlist_format::expand_uri(&uri);
+        let is_playlist_file = items.len() != 1 || items[0].uri != uri;
+        let mut last_item = None;
+        let player_id = &self.player;
+        with_player!(player_id player_data {
+            last_item = player_data.playlist.items().last().map(|item| item.id);
+        });
+        match last_item {
+            Some(id) if !is_playlist_file => {
+                let player = &self.player;
+                with_mut_player!(player player_data {
+                    player_data.add_item(player, &uri, Some(id), true);
+                });
+            }
+            _ => self.load_playlist(items),
+        }
+        Ok(())
     }
 
     async fn playback_status(&self) -> fdo::Result<PlaybackStatus> {
@@ -1147,13 +2539,12 @@ impl LocalPlayerInterface for ChannelPlayer {
     }
 
     async fn loop_status(&self) -> fdo::Result<LoopStatus> {
-        Ok(LoopStatus::None)
+        Ok(self.loop_mode().into())
     }
 
-    async fn set_loop_status(&self, _loop_status: LoopStatus) -> zbus::Result<()> {
-        Err(zbus::Error::from(fdo::Error::NotSupported(
-            "SetLoopStatus is not supported".into(),
-        )))
+    async fn set_loop_status(&self, loop_status: LoopStatus) -> zbus::Result<()> {
+        self.set_loop_mode(loop_status.into());
+        Ok(())
     }
 
     async fn rate(&self) -> fdo::Result<PlaybackRate> {
@@ -1161,18 +2552,17 @@ impl LocalPlayerInterface for ChannelPlayer {
     }
 
     async fn set_rate(&self, rate: PlaybackRate) -> zbus::Result<()> {
-        self.player.set_rate(rate);
+        self.set_playback_rate(rate);
         Ok(())
     }
 
     async fn shuffle(&self) -> fdo::Result<bool> {
-        Ok(false)
+        Ok(self.is_shuffled())
     }
 
-    async fn set_shuffle(&self, _shuffle: bool) -> zbus::Result<()> {
-        Err(zbus::Error::from(fdo::Error::NotSupported(
-            "SetShuffle is not supported".into(),
-        )))
+    async fn set_shuffle(&self, shuffle: bool) -> zbus::Result<()> {
+        ChannelPlayer::set_shuffle(self, shuffle);
+        Ok(())
     }
 
     async fn metadata(&self) -> fdo::Result<Metadata> {
@@ -1248,3 +2638,73 @@ impl LocalPlayerInterface for ChannelPlayer {
         Ok(true)
     }
 }
+
+impl LocalTrackListInterface for ChannelPlayer {
+    async fn get_tracks_metadata(&self, track_ids: Vec<TrackId>) -> fdo::Result<Vec<Metadata>> {
+        let mut result = vec![];
+        let player_id = &self.player;
+        with_player!(player_id player_data {
+            let items = player_data.playlist.items();
+            for track_id in track_ids {
+                let Some(index) = playlist_item_id(&track_id).and_then(|id| player_data.playlist.position_of(id)) else {
+                    continue;
+                };
+                result.push(track_metadata(&items[index]));
+            }
+        });
+        Ok(result)
+    }
+
+    async fn add_track(&self, uri: Uri, after_track: TrackId, set_as_current: bool) -> fdo::Result<()> {
+        let after = if after_track == TrackId::NO_TRACK {
+            None
+        } else {
+            Some(playlist_item_id(&after_track).ok_or(fdo::Error::InvalidArgs("Unknown track".into()))?)
+        };
+        let mut result = false;
+        let player = &self.player;
+        with_mut_player!(player player_data {
+            result = player_data.add_item(player, &uri, after, set_as_current);
+        });
+        if result {
+            Ok(())
+        } else {
+            Err(fdo::Error::InvalidArgs("Unknown track".into()))
+        }
+    }
+
+    async fn remove_track(&self, track_id: TrackId) -> fdo::Result<()> {
+        let mut index = None;
+        let player_id = &self.player;
+        with_player!(player_id player_data {
+            index = playlist_item_id(&track_id).and_then(|id| player_data.playlist.position_of(id));
+        });
+        let index = index.ok_or(fdo::Error::InvalidArgs("Unknown track".into()))?;
+        self.remove_from_playlist(index);
+        Ok(())
+    }
+
+    async fn go_to(&self, track_id: TrackId) -> fdo::Result<()> {
+        let mut index = None;
+        let player_id = &self.player;
+        with_player!(player_id player_data {
+            index = playlist_item_id(&track_id).and_then(|id| player_data.playlist.position_of(id));
+        });
+        let index = index.ok_or(fdo::Error::InvalidArgs("Unknown track".into()))?;
+        self.jump_to(index);
+        Ok(())
+    }
+
+    async fn tracks(&self) -> fdo::Result<Vec<TrackId>> {
+        let mut result = vec![];
+        let player_id = &self.player;
+        with_player!(player_id player_data {
+            result = player_data.playlist.items().iter().map(track_id).collect();
+        });
+        Ok(result)
+    }
+
+    async fn can_edit_tracks(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+}
@@ -0,0 +1,238 @@
+use std::string;
+
+// Chapters starting this close to the current position are skipped when going to the next one, seeking
+// might have stopped slightly before the start of the chapter that was just reached.
+const SKIP_THRESHOLD: gst::ClockTime = gst::ClockTime::from_seconds(1);
+// Going back from further than this into a chapter restarts it, instead of going to the previous one.
+const RESTART_THRESHOLD: gst::ClockTime = gst::ClockTime::from_seconds(3);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub start: gst::ClockTime,
+    pub title: Option<string::String>,
+}
+
+/// Chapters of the first edition of a table of contents that has some, sorted by start time. Nested
+/// chapters are ignored.
+pub fn from_toc(toc: &gst::TocRef) -> Vec<Chapter> {
+    from_entries(&toc.entries())
+}
+
+fn from_entries(entries: &[gst::TocEntry]) -> Vec<Chapter> {
+    let mut chapters = vec![];
+    for entry in entries {
+        if entry.entry_type() == gst::TocEntryType::Chapter {
+            if let Some(chapter) = chapter(entry) {
+                chapters.push(chapter);
+            }
+        } else if chapters.is_empty() {
+            // Editions, or any other alternative, only the first one with chapters is kept.
+            chapters = from_entries(&entry.sub_entries());
+        }
+    }
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters
+}
+
+fn chapter(entry: &gst::TocEntryRef) -> Option<Chapter> {
+    let (start, _) = entry.start_stop_times()?;
+    let start = gst::ClockTime::from_nseconds(u64::try_from(start).ok()?);
+    let title = entry
+        .tags()
+        .and_then(|tags| tags.get::<gst::tags::Title>().map(|title| title.get().to_string()))
+        .filter(|title| !title.is_empty());
+    Some(Chapter { start, title })
+}
+
+/// Index of the chapter being played.
+pub fn current(chapters: &[Chapter], position: gst::ClockTime) -> Option<usize> {
+    chapters.iter().rposition(|chapter| chapter.start <= position)
+}
+
+pub fn next(chapters: &[Chapter], position: gst::ClockTime) -> Option<&Chapter> {
+    chapters
+        .iter()
+        .find(|chapter| chapter.start > position + SKIP_THRESHOLD)
+}
+
+/// The start of the current chapter, or of the previous one when the current one just started.
+pub fn previous(chapters: &[Chapter], position: gst::ClockTime) -> Option<&Chapter> {
+    chapters
+        .iter()
+        .rev()
+        .find(|chapter| chapter.start + RESTART_THRESHOLD <= position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gst::prelude::*;
+    use std::path;
+
+    fn seconds(s: u64) -> gst::ClockTime {
+        gst::ClockTime::from_seconds(s)
+    }
+
+    fn toc_entry(entry_type: gst::TocEntryType, uid: &str, start: u64, title: Option<&str>) -> gst::TocEntry {
+        let mut entry = gst::TocEntry::new(entry_type, uid);
+        let entry_mut = entry.get_mut().unwrap();
+        entry_mut.set_start_stop_times(seconds(start).nseconds() as i64, -1);
+        if let Some(title) = title {
+            let mut tags = gst::TagList::new();
+            tags.get_mut()
+                .unwrap()
+                .add::<gst::tags::Title>(&title, gst::TagMergeMode::Replace);
+            entry_mut.set_tags(tags);
+        }
+        entry
+    }
+
+    fn toc(editions: &[&[(u64, Option<&str>)]]) -> gst::Toc {
+        let mut toc = gst::Toc::new(gst::TocScope::Global);
+        for (i, chapters) in editions.iter().enumerate() {
+            let mut edition = gst::TocEntry::new(gst::TocEntryType::Edition, &format!("edition-{i}"));
+            for (j, (start, title)) in chapters.iter().enumerate() {
+                let chapter = toc_entry(gst::TocEntryType::Chapter, &format!("chapter-{i}-{j}"), *start, *title);
+                edition.get_mut().unwrap().append_sub_entry(chapter);
+            }
+            toc.get_mut().unwrap().append_entry(edition);
+        }
+        toc
+    }
+
+    fn starts(chapters: &[Chapter]) -> Vec<u64> {
+        chapters.iter().map(|chapter| chapter.start.seconds()).collect()
+    }
+
+    #[test]
+    fn chapters_from_toc() {
+        gst::init().unwrap();
+        let toc = toc(&[
+            &[],
+            &[
+                (300, Some("Second")),
+                (0, Some("Opening")),
+                (120, None),
+                (600, Some("")),
+            ],
+            &[(0, Some("Director's cut"))],
+        ]);
+        let chapters = from_toc(&toc);
+        assert_eq!(starts(&chapters), [0, 120, 300, 600]);
+        assert_eq!(chapters[0].title.as_deref(), Some("Opening"));
+        assert_eq!(chapters[1].title, None);
+        assert_eq!(chapters[3].title, None);
+
+        // Chapters right below the table of contents, and nested ones.
+        let mut toc = gst::Toc::new(gst::TocScope::Global);
+        let mut chapter = toc_entry(gst::TocEntryType::Chapter, "a", 0, Some("A"));
+        let nested = toc_entry(gst::TocEntryType::Chapter, "a1", 30, Some("A.1"));
+        chapter.get_mut().unwrap().append_sub_entry(nested);
+        toc.get_mut().unwrap().append_entry(chapter);
+        let chapter = toc_entry(gst::TocEntryType::Chapter, "b", 60, Some("B"));
+        toc.get_mut().unwrap().append_entry(chapter);
+        assert_eq!(starts(&from_toc(&toc)), [0, 60]);
+
+        assert!(from_toc(&gst::Toc::new(gst::TocScope::Global)).is_empty());
+    }
+
+    #[test]
+    fn navigation() {
+        let chapters: Vec<Chapter> = [0, 120, 300]
+            .iter()
+            .map(|start| Chapter {
+                start: seconds(*start),
+                title: None,
+            })
+            .collect();
+        let next = |s| next(&chapters, seconds(s)).map(|chapter| chapter.start.seconds());
+        let previous = |s| previous(&chapters, seconds(s)).map(|chapter| chapter.start.seconds());
+
+        assert_eq!(current(&chapters, seconds(0)), Some(0));
+        assert_eq!(current(&chapters, seconds(200)), Some(1));
+        assert_eq!(current(&chapters, seconds(3000)), Some(2));
+        assert_eq!(current(&[], seconds(10)), None);
+
+        assert_eq!(next(0), Some(120));
+        assert_eq!(next(120), Some(300));
+        assert_eq!(next(300), None);
+
+        // Restart the current chapter, unless it just started.
+        assert_eq!(previous(200), Some(120));
+        assert_eq!(previous(121), Some(0));
+        assert_eq!(previous(2), None);
+    }
+
+    // Write a short Matroska file with the given table of contents.
+    fn write_mkv(path: &path::Path, toc: &gst::Toc) {
+        let pipeline = gst::parse::launch(&format!(
+            "audiotestsrc num-buffers=20 samplesperbuffer=4000 ! audio/x-raw,format=S16LE,rate=8000,channels=1 ! \
+             matroskamux name=mux ! filesink location=\"{}\"",
+            path.display()
+        ))
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+        let mux = pipeline.by_name("mux").unwrap();
+        mux.dynamic_cast_ref::<gst::TocSetter>().unwrap().set_toc(Some(toc));
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let message = pipeline.bus().unwrap().timed_pop_filtered(
+            gst::ClockTime::from_seconds(10),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        pipeline.set_state(gst::State::Null).unwrap();
+        assert!(matches!(message.unwrap().view(), gst::MessageView::Eos(_)));
+    }
+
+    // The table of contents found by the Matroska demuxer.
+    fn read_mkv_toc(path: &path::Path) -> Option<gst::Toc> {
+        let pipeline = gst::parse::launch(&format!(
+            "filesrc location=\"{}\" ! matroskademux ! fakesink",
+            path.display()
+        ))
+        .unwrap();
+        pipeline.set_state(gst::State::Paused).unwrap();
+        let bus = pipeline.bus().unwrap();
+        let mut toc = None;
+        while let Some(message) = bus.timed_pop_filtered(
+            gst::ClockTime::from_seconds(10),
+            &[
+                gst::MessageType::Toc,
+                gst::MessageType::AsyncDone,
+                gst::MessageType::Error,
+            ],
+        ) {
+            match message.view() {
+                gst::MessageView::Toc(message) => toc = Some(message.toc().0),
+                _ => break,
+            }
+        }
+        pipeline.set_state(gst::State::Null).unwrap();
+        toc
+    }
+
+    #[test]
+    fn chapters_from_mkv() {
+        gst::init().unwrap();
+        let missing: Vec<_> = ["audiotestsrc", "matroskamux", "matroskademux"]
+            .into_iter()
+            .filter(|name| gst::ElementFactory::find(name).is_none())
+            .collect();
+        if !missing.is_empty() {
+            eprintln!("Skipping, missing GStreamer elements: {missing:?}");
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chapters.mkv");
+        write_mkv(&path, &toc(&[&[(0, Some("Intro")), (4, Some("Verse")), (7, None)]]));
+        let chapters = from_toc(&read_mkv_toc(&path).expect("No table of contents"));
+        assert_eq!(starts(&chapters), [0, 4, 7]);
+        assert_eq!(chapters[0].title.as_deref(), Some("Intro"));
+        assert_eq!(chapters[1].title.as_deref(), Some("Verse"));
+
+        let path = dir.path().join("no-chapters.mkv");
+        write_mkv(&path, &gst::Toc::new(gst::TocScope::Global));
+        assert!(read_mkv_toc(&path).is_none_or(|toc| from_toc(&toc).is_empty()));
+    }
+}
@@ -3,3 +3,6 @@ pub static SEEK_FORWARD_OFFSET: gst::ClockTime = gst::ClockTime::from_mseconds(5
 pub static DRAG_SEEK_FORWARD_OFFSET: gst::ClockTime = gst::ClockTime::from_mseconds(2000);
 
 pub static SUB_FILE_EXTENSIONS: [&str; 3] = ["srt", "sub", "ass"];
+
+// Media listed on the welcome page and in the Recent menu.
+pub static RECENT_MEDIA_LIMIT: usize = 10;
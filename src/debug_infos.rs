@@ -37,6 +37,8 @@ pub struct DebugInfos {
 
 // TODO: graph dumps
 
+// Only built from the lazily initialized lists below, which newer compilers don't see in test builds.
+#[cfg_attr(test, allow(dead_code))]
 #[derive(Clone)]
 pub struct Codec {
     name: &'static str,
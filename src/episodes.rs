@@ -0,0 +1,225 @@
+use crate::media_directory;
+use gio::prelude::*;
+use regex::Regex;
+use std::path;
+use std::string;
+
+lazy_static! {
+    // "Show.S01E03", "Show s1 e3", "Show.S01.E03"
+    static ref SEASON_EPISODE: Regex =
+        Regex::new(r"(?i)(?:^|[^a-z0-9])s(\d{1,2})[ ._-]?e(\d{1,3})(?:[^0-9]|$)").unwrap();
+    // "Show - 1x03"
+    static ref SEASON_X_EPISODE: Regex = Regex::new(r"(?i)(?:^|[^a-z0-9])(\d{1,2})x(\d{2,3})(?:[^0-9]|$)").unwrap();
+    // "Show - Episode 3", "Show Ep.03", "podcast_ep42"
+    static ref EPISODE: Regex = Regex::new(r"(?i)(?:^|[^a-z])(?:episode|ep)[ ._-]*(\d{1,3})(?:[^0-9]|$)").unwrap();
+    // "[Group] Show - 03 (1080p) [CRC32]", release tags are dropped before looking for the number.
+    static ref TAGS: Regex = Regex::new(r"\[[^\]]*\]|\([^)]*\)").unwrap();
+    static ref TRAILING_NUMBER: Regex = Regex::new(r"(?i)(?:^|[^a-z0-9])(\d{1,3})(?:v\d)?$").unwrap();
+}
+
+/// Position of a file in a series, guessed from its name.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Episode {
+    /// Normalized name of the series, the part of the file name before the episode number.
+    pub series: string::String,
+    pub season: Option<u32>,
+    pub number: u32,
+}
+
+impl Episode {
+    pub fn parse(file_name: &str) -> Option<Self> {
+        let stem = path::Path::new(file_name).file_stem()?.to_string_lossy();
+
+        for re in [&*SEASON_EPISODE, &*SEASON_X_EPISODE] {
+            if let Some(captures) = re.captures(&stem) {
+                return Some(Self {
+                    series: normalize(&stem[..captures.get(0)?.start()]),
+                    season: captures[1].parse().ok(),
+                    number: captures[2].parse().ok()?,
+                });
+            }
+        }
+
+        if let Some(captures) = EPISODE.captures(&stem) {
+            return Some(Self {
+                series: normalize(&stem[..captures.get(0)?.start()]),
+                season: None,
+                number: captures[1].parse().ok()?,
+            });
+        }
+
+        let stripped = TAGS.replace_all(&stem, "");
+        let stripped = stripped.trim_end_matches([' ', '.', '_', '-']);
+        let captures = TRAILING_NUMBER.captures(stripped)?;
+        let series = normalize(&stripped[..captures.get(0)?.start()]);
+        // A lone number is more likely a track of an album than an episode.
+        if series.is_empty() {
+            return None;
+        }
+        Some(Self {
+            series,
+            season: None,
+            number: captures[1].parse().ok()?,
+        })
+    }
+
+    fn follows(&self, other: &Self) -> bool {
+        self.series == other.series
+            && self.season.is_some() == other.season.is_some()
+            && (self.season, self.number) > (other.season, other.number)
+    }
+}
+
+// Lower-case words separated by single spaces, so that "The.Show" and "The Show -" compare equal.
+fn normalize(s: &str) -> string::String {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Names of the episodes following `file_name` among its siblings, in episode order. When an episode
+/// is available under several names, the first one is kept.
+pub fn following<'a>(file_name: &str, siblings: &'a [string::String]) -> Vec<&'a str> {
+    let Some(current) = Episode::parse(file_name) else {
+        return vec![];
+    };
+    let mut episodes: Vec<(Episode, &str)> = siblings
+        .iter()
+        .filter_map(|name| Episode::parse(name).map(|episode| (episode, name.as_str())))
+        .filter(|(episode, _)| episode.follows(&current))
+        .collect();
+    episodes.sort_by_key(|(episode, _)| (episode.season, episode.number));
+    episodes.dedup_by_key(|(episode, _)| (episode.season, episode.number));
+    episodes.into_iter().map(|(_, name)| name).collect()
+}
+
+/// URIs of the media files of the same directory that come after the given episode.
+pub fn following_siblings(path: &path::Path) -> Vec<string::String> {
+    let (Some(directory), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return vec![];
+    };
+    let uris = media_directory::expand(directory, 0);
+    let names: Vec<string::String> = uris
+        .iter()
+        .map(|uri| {
+            gio::File::for_uri(uri)
+                .basename()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+        .collect();
+    following(&file_name.to_string_lossy(), &names)
+        .into_iter()
+        .filter_map(|name| names.iter().position(|n| n == name))
+        .map(|index| uris[index].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(series: &str, season: Option<u32>, number: u32) -> Option<Episode> {
+        Some(Episode {
+            series: series.to_string(),
+            season,
+            number,
+        })
+    }
+
+    #[test]
+    fn parse_names() {
+        let table = [
+            ("Show.S01E03.mkv", episode("show", Some(1), 3)),
+            (
+                "The.Expanse.S02E05.1080p.WEB-DL.DD5.1.H264.mkv",
+                episode("the expanse", Some(2), 5),
+            ),
+            ("the expanse s2e6 720p.mp4", episode("the expanse", Some(2), 6)),
+            (
+                "Doctor.Who.2005.S03E10.Blink.avi",
+                episode("doctor who 2005", Some(3), 10),
+            ),
+            (
+                "Twin Peaks - S01.E02 - Traces to Nowhere.mkv",
+                episode("twin peaks", Some(1), 2),
+            ),
+            (
+                "Breaking Bad - 1x03 - ...And the Bag's in the River.mkv",
+                episode("breaking bad", Some(1), 3),
+            ),
+            (
+                "Planet Earth II - Episode 3 - Jungles.mkv",
+                episode("planet earth ii", None, 3),
+            ),
+            ("Cosmos Ep.07 [720p].mp4", episode("cosmos", None, 7)),
+            ("podcast_ep142.mp3", episode("podcast", None, 142)),
+            (
+                "[SubsPlease] Sousou no Frieren - 12 (1080p) [A1B2C3D4].mkv",
+                episode("sousou no frieren", None, 12),
+            ),
+            (
+                "[Erai-raws] Dungeon Meshi - 05v2 [1080p].mkv",
+                episode("dungeon meshi", None, 5),
+            ),
+            ("Lecture 07.mp4", episode("lecture", None, 7)),
+            ("CS50 2023 - Lecture 10.mp4", episode("cs50 2023 lecture", None, 10)),
+            ("Blade Runner 2049 (2017).mkv", None),
+            ("Holiday video.mp4", None),
+            ("01.flac", None),
+            ("1920x1080 sample.mkv", None),
+            ("Movie.2019.1080p.BluRay.x264.mkv", None),
+        ];
+        for (name, expected) in table {
+            assert_eq!(Episode::parse(name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn following_episodes() {
+        let siblings: Vec<string::String> = [
+            "Show.S01E01.mkv",
+            "Show.S01E02.mkv",
+            "Show.S01E03.mkv",
+            "Show.S01E04.1080p.mkv",
+            "Show.S01E04.720p.mkv",
+            "Show.S02E01.mkv",
+            "Show.S01E10.mkv",
+            "Other.Show.S01E05.mkv",
+            "Show - Episode 5.mkv",
+            "notes.mkv",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+        assert_eq!(
+            following("Show.S01E03.mkv", &siblings),
+            ["Show.S01E04.1080p.mkv", "Show.S01E10.mkv", "Show.S02E01.mkv"]
+        );
+        assert!(following("Show.S02E01.mkv", &siblings).is_empty());
+        assert!(following("notes.mkv", &siblings).is_empty());
+    }
+
+    #[test]
+    fn following_siblings_in_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        media_directory::create_files(
+            root,
+            &[
+                "Lecture 01.mp4",
+                "Lecture 02.mp4",
+                "Lecture 10.mp4",
+                "Lecture 03.txt",
+                "Intro.mp4",
+            ],
+        );
+        let names: Vec<_> = following_siblings(&root.join("Lecture 01.mp4"))
+            .iter()
+            .map(|uri| gio::File::for_uri(uri).basename().unwrap())
+            .collect();
+        assert_eq!(names, [path::PathBuf::from("Lecture 02.mp4"), "Lecture 10.mp4".into()]);
+    }
+}
@@ -14,6 +14,8 @@ extern crate lazy_static;
 extern crate serde_derive;
 
 use crate::gst_play::prelude::PlayStreamInfoExt;
+use crate::session::Session;
+use crate::settings::{ResumeMode, Settings};
 use gstreamer::glib;
 
 use clap::Parser;
@@ -23,33 +25,103 @@ use gdk::prelude::*;
 use gettextrs::*;
 use gio::prelude::*;
 use gtk::gdk;
-use std::cell::RefCell;
+use gtk::prelude::RecentManagerExt;
+use std::cell::{Cell, RefCell};
 use std::env;
 use std::fs::create_dir_all;
 use std::path::PathBuf;
 
+mod bookmarks;
+mod cache_command;
 mod channel_player;
+mod chapters;
 mod config;
 mod constants;
 mod debug_infos;
+mod episodes;
 mod i18n;
+mod media_cache;
+mod media_directory;
+mod playlist;
+mod playlist_format;
+mod session;
+mod settings;
 use channel_player::{AudioVisualization, ChannelPlayer, PlaybackState, PlayerEvent, SeekDirection, SubtitleTrack};
+use playlist::{LoopMode, PlaylistItem};
 mod ui_context;
-use ui_context::{create_app, UIContext};
+use ui_context::{create_app, PlaylistRow, RecentRow, UIContext};
 
 #[cfg(target_os = "macos")]
 mod iokit_sleep_disabler;
 
 #[derive(clap::Parser, Debug)]
-#[clap(about, version, author)]
+#[clap(about, version, author, args_conflicts_with_subcommands = true)]
 struct Opt {
-    /// Activate incognito mode. Playback position won't be recorded/loaded to/from the media cache
+    /// Activate incognito mode. Playback position and session won't be recorded/loaded to/from the cache
     #[clap(short, long)]
     incognito: bool,
 
-    /// Files to play
+    /// Repeat the current track or the whole playlist
+    #[clap(long = "loop", value_enum, value_name = "MODE", num_args = 0..=1, require_equals = true, default_missing_value = "playlist")]
+    loop_mode: Option<LoopMode>,
+
+    /// Play the playlist in random order
+    #[clap(long)]
+    shuffle: bool,
+
+    /// Seed of the shuffle random number generator, for a reproducible play order
+    #[clap(long, value_name = "SEED")]
+    shuffle_seed: Option<u64>,
+
+    /// Overlap consecutive audio tracks, fading from one to the next over the given number of seconds
+    #[clap(long, value_name = "SECONDS", value_parser = clap::value_parser!(u32).range(0..=12))]
+    crossfade: Option<u32>,
+
+    /// Save the playlist to the given M3U8 or XSPF file when quitting
+    #[clap(long, value_name = "FILE")]
+    save_playlist: Option<PathBuf>,
+
+    /// Add the files to the end of the playlist of the running instance instead of replacing it
+    #[clap(long, conflicts_with = "play_next")]
+    enqueue: bool,
+
+    /// Queue the files right after the item being played by the running instance
+    #[clap(long)]
+    play_next: bool,
+
+    /// Files to play. Put them after `--` when the first one is named like a subcommand, as in
+    /// `glide -- cache`
     #[clap(name = "FILE", value_parser)]
     files: Vec<PathBuf>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Inspect or edit the playback history, then exit
+    #[clap(subcommand)]
+    Cache(cache_command::CacheCommand),
+}
+
+impl Opt {
+    fn open_mode(&self) -> OpenMode {
+        if self.enqueue {
+            OpenMode::Enqueue
+        } else if self.play_next {
+            OpenMode::PlayNext
+        } else {
+            OpenMode::Replace
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OpenMode {
+    Replace,
+    Enqueue,
+    PlayNext,
 }
 
 struct VideoPlayer {
@@ -66,19 +138,55 @@ struct VideoPlayer {
     audio_track_action: gio::SimpleAction,
     video_track_action: gio::SimpleAction,
     open_media_action: gio::SimpleAction,
+    save_playlist_action: gio::SimpleAction,
     open_subtitle_file_action: gio::SimpleAction,
     audio_mute_action: gio::SimpleAction,
     volume_increase_action: gio::SimpleAction,
     volume_decrease_action: gio::SimpleAction,
     dump_pipeline_action: gio::SimpleAction,
     open_sync_window_action: gio::SimpleAction,
+    open_subtitle_style_window_action: gio::SimpleAction,
     show_shortcuts_action: gio::SimpleAction,
     audio_offset_reset_action: gio::SimpleAction,
     subtitle_offset_reset_action: gio::SimpleAction,
     video_frame_step_action: gio::SimpleAction,
     speed_increase_action: gio::SimpleAction,
     speed_decrease_action: gio::SimpleAction,
+    loop_action: gio::SimpleAction,
+    shuffle_action: gio::SimpleAction,
+    crossfade_action: gio::SimpleAction,
+    playlist_jump_action: gio::SimpleAction,
+    playlist_remove_action: gio::SimpleAction,
+    playlist_play_next_action: gio::SimpleAction,
+    playlist_move_action: gio::SimpleAction,
+    restore_session_action: gio::SimpleAction,
+    restore_session_on_startup_action: gio::SimpleAction,
+    queue_next_episodes_action: gio::SimpleAction,
+    resume_mode_action: gio::SimpleAction,
+    resume_playback_action: gio::SimpleAction,
+    start_over_action: gio::SimpleAction,
+    open_recent_action: gio::SimpleAction,
+    bookmark_add_action: gio::SimpleAction,
+    bookmark_next_action: gio::SimpleAction,
+    bookmark_previous_action: gio::SimpleAction,
+    bookmark_jump_action: gio::SimpleAction,
+    bookmark_rename_action: gio::SimpleAction,
+    bookmark_remove_action: gio::SimpleAction,
+    chapter_next_action: gio::SimpleAction,
+    chapter_previous_action: gio::SimpleAction,
+    ab_repeat_start_action: gio::SimpleAction,
+    ab_repeat_end_action: gio::SimpleAction,
+    ab_repeat_clear_action: gio::SimpleAction,
+    chapter_jump_action: gio::SimpleAction,
+    // Index of the chapter shown in the window subtitle.
+    current_chapter: Cell<Option<usize>>,
     player_receiver: Option<async_channel::Receiver<PlayerEvent>>,
+    save_playlist_path: Option<PathBuf>,
+    session_path: Option<PathBuf>,
+    settings: Settings,
+    // Pending save of the settings, while the subtitle style is being edited.
+    settings_save_source: RefCell<Option<glib::SourceId>>,
+    incognito: bool,
 }
 
 thread_local!(
@@ -134,6 +242,9 @@ impl VideoPlayer {
         let open_media_action = gio::SimpleAction::new("open-media", None);
         gtk_app.add_action(&open_media_action);
 
+        let save_playlist_action = gio::SimpleAction::new("save-playlist", None);
+        gtk_app.add_action(&save_playlist_action);
+
         let open_subtitle_file_action = gio::SimpleAction::new("open-subtitle-file", None);
         gtk_app.add_action(&open_subtitle_file_action);
 
@@ -173,6 +284,9 @@ impl VideoPlayer {
         let open_sync_window_action = gio::SimpleAction::new("open-sync-window", None);
         gtk_app.add_action(&open_sync_window_action);
 
+        let open_subtitle_style_window_action = gio::SimpleAction::new("open-subtitle-style-window", None);
+        gtk_app.add_action(&open_subtitle_style_window_action);
+
         let show_shortcuts_action = gio::SimpleAction::new("show-shortcuts", None);
         gtk_app.add_action(&show_shortcuts_action);
 
@@ -191,6 +305,99 @@ impl VideoPlayer {
         let speed_decrease_action = gio::SimpleAction::new("speed-decrease", None);
         gtk_app.add_action(&speed_decrease_action);
 
+        let loop_mode = options.loop_mode.unwrap_or_default();
+        let loop_action =
+            gio::SimpleAction::new_stateful("loop", glib::VariantTy::new("s").ok(), &loop_mode.as_str().to_variant());
+        gtk_app.add_action(&loop_action);
+
+        let shuffle_action = gio::SimpleAction::new_stateful("shuffle", None, &options.shuffle.to_variant());
+        gtk_app.add_action(&shuffle_action);
+
+        let crossfade_action = gio::SimpleAction::new_stateful(
+            "crossfade",
+            glib::VariantTy::new("u").ok(),
+            &options.crossfade.unwrap_or(0).to_variant(),
+        );
+        gtk_app.add_action(&crossfade_action);
+
+        let playlist_jump_action = gio::SimpleAction::new("playlist-jump", glib::VariantTy::new("u").ok());
+        gtk_app.add_action(&playlist_jump_action);
+
+        let playlist_remove_action = gio::SimpleAction::new("playlist-remove", glib::VariantTy::new("u").ok());
+        gtk_app.add_action(&playlist_remove_action);
+
+        let playlist_play_next_action = gio::SimpleAction::new("playlist-play-next", glib::VariantTy::new("u").ok());
+        gtk_app.add_action(&playlist_play_next_action);
+
+        let playlist_move_action = gio::SimpleAction::new("playlist-move", glib::VariantTy::new("(uu)").ok());
+        gtk_app.add_action(&playlist_move_action);
+
+        let settings = Settings::load();
+
+        let restore_session_action = gio::SimpleAction::new("restore-session", None);
+        gtk_app.add_action(&restore_session_action);
+
+        let restore_session_on_startup_action = gio::SimpleAction::new_stateful(
+            "restore-session-on-startup",
+            None,
+            &settings.restore_session.to_variant(),
+        );
+        gtk_app.add_action(&restore_session_on_startup_action);
+
+        let queue_next_episodes_action =
+            gio::SimpleAction::new_stateful("queue-next-episodes", None, &settings.queue_next_episodes.to_variant());
+        gtk_app.add_action(&queue_next_episodes_action);
+
+        let resume_mode_action = gio::SimpleAction::new_stateful(
+            "resume-mode",
+            glib::VariantTy::new("s").ok(),
+            &settings.resume.as_str().to_variant(),
+        );
+        gtk_app.add_action(&resume_mode_action);
+
+        let resume_playback_action = gio::SimpleAction::new("resume-playback", None);
+        gtk_app.add_action(&resume_playback_action);
+
+        let start_over_action = gio::SimpleAction::new("start-over", None);
+        gtk_app.add_action(&start_over_action);
+
+        let open_recent_action = gio::SimpleAction::new("open-recent", glib::VariantTy::new("s").ok());
+        gtk_app.add_action(&open_recent_action);
+
+        let bookmark_add_action = gio::SimpleAction::new("bookmark-add", None);
+        // Bookmarks are stored in the media cache, which isn't used in incognito mode.
+        bookmark_add_action.set_enabled(!options.incognito);
+        gtk_app.add_action(&bookmark_add_action);
+        let bookmark_next_action = gio::SimpleAction::new("bookmark-next", None);
+        bookmark_next_action.set_enabled(false);
+        gtk_app.add_action(&bookmark_next_action);
+        let bookmark_previous_action = gio::SimpleAction::new("bookmark-previous", None);
+        bookmark_previous_action.set_enabled(false);
+        gtk_app.add_action(&bookmark_previous_action);
+        let bookmark_jump_action = gio::SimpleAction::new("bookmark-jump", glib::VariantTy::new("(st)").ok());
+        gtk_app.add_action(&bookmark_jump_action);
+        let bookmark_rename_action = gio::SimpleAction::new("bookmark-rename", glib::VariantTy::new("(st)").ok());
+        gtk_app.add_action(&bookmark_rename_action);
+        let bookmark_remove_action = gio::SimpleAction::new("bookmark-remove", glib::VariantTy::new("(st)").ok());
+        gtk_app.add_action(&bookmark_remove_action);
+
+        let chapter_next_action = gio::SimpleAction::new("chapter-next", None);
+        chapter_next_action.set_enabled(false);
+        gtk_app.add_action(&chapter_next_action);
+        let chapter_previous_action = gio::SimpleAction::new("chapter-previous", None);
+        chapter_previous_action.set_enabled(false);
+        gtk_app.add_action(&chapter_previous_action);
+        let chapter_jump_action = gio::SimpleAction::new("chapter-jump", glib::VariantTy::new("u").ok());
+        gtk_app.add_action(&chapter_jump_action);
+
+        let ab_repeat_start_action = gio::SimpleAction::new("ab-repeat-start", None);
+        gtk_app.add_action(&ab_repeat_start_action);
+        let ab_repeat_end_action = gio::SimpleAction::new("ab-repeat-end", None);
+        gtk_app.add_action(&ab_repeat_end_action);
+        let ab_repeat_clear_action = gio::SimpleAction::new("ab-repeat-clear", None);
+        ab_repeat_clear_action.set_enabled(false);
+        gtk_app.add_action(&ab_repeat_clear_action);
+
         let about = gio::SimpleAction::new("about", None);
         about.connect_activate(move |_, _| {
             with_video_player!(video_player {
@@ -222,11 +429,46 @@ impl VideoPlayer {
         });
         gtk_app.add_action(&quit);
 
-        gtk_app.connect_open(move |app, files, _| {
+        // Also emitted in the primary instance when Glide is launched again, so that the open mode of that
+        // invocation applies to the files it was given.
+        gtk_app.connect_command_line(|app, command_line| {
+            let options = match Opt::try_parse_from(command_line.arguments()) {
+                Ok(options) => options,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return glib::ExitCode::FAILURE;
+                }
+            };
             app.activate();
+            // Playback options given to a new invocation apply to the running instance too, through the
+            // actions of the Playback menu so that it stays in sync.
+            if let Some(loop_mode) = options.loop_mode {
+                app.change_action_state("loop", &loop_mode.as_str().to_variant());
+            }
+            if let Some(seed) = options.shuffle_seed {
+                with_video_player!(player {
+                    player.player.set_shuffle_seed(seed);
+                });
+            }
+            if options.shuffle {
+                app.change_action_state("shuffle", &true.to_variant());
+            }
+            if let Some(seconds) = options.crossfade {
+                app.change_action_state("crossfade", &seconds.to_variant());
+            }
+            let uris: Vec<std::string::String> = options
+                .files
+                .iter()
+                .map(|file| command_line_uri(command_line, file))
+                .collect();
             with_mut_video_player!(player {
-                player.open_files(files);
+                if uris.is_empty() && options.open_mode() == OpenMode::Replace {
+                    player.restore_session_on_startup();
+                } else {
+                    player.open_uris(&uris, options.open_mode());
+                }
             });
+            glib::ExitCode::SUCCESS
         });
 
         let app_clone = gtk_app.clone();
@@ -239,8 +481,22 @@ impl VideoPlayer {
             create_dir_all(d.cache_dir()).unwrap();
             cache_dir_path = Some(d.cache_dir().to_path_buf());
         }
+        let session_path = match cache_dir_path {
+            Some(ref dir) if !options.incognito => Some(dir.join("session.json")),
+            _ => None,
+        };
+        restore_session_action.set_enabled(session_path.as_ref().is_some_and(|path| path.exists()));
 
         let player = ChannelPlayer::new(app_clone, player_sender, options.incognito, cache_dir_path)?;
+        player.set_loop_mode(loop_mode);
+        if let Some(seed) = options.shuffle_seed {
+            player.set_shuffle_seed(seed);
+        }
+        player.set_shuffle(options.shuffle);
+        player.set_crossfade_duration(gst::ClockTime::from_seconds(options.crossfade.unwrap_or(0).into()));
+        player.set_resume_mode(settings.resume);
+        player.set_subtitle_style(&settings.subtitle_style);
+        ui_context.set_subtitle_style(&settings.subtitle_style);
 
         Ok(Self {
             player,
@@ -256,29 +512,113 @@ impl VideoPlayer {
             audio_track_action,
             video_track_action,
             open_media_action,
+            save_playlist_action,
             open_subtitle_file_action,
             audio_mute_action,
             volume_increase_action,
             volume_decrease_action,
             dump_pipeline_action,
             open_sync_window_action,
+            open_subtitle_style_window_action,
             show_shortcuts_action,
             audio_offset_reset_action,
             subtitle_offset_reset_action,
             video_frame_step_action,
             speed_increase_action,
             speed_decrease_action,
+            loop_action,
+            shuffle_action,
+            crossfade_action,
+            playlist_jump_action,
+            playlist_remove_action,
+            playlist_play_next_action,
+            playlist_move_action,
+            restore_session_action,
+            restore_session_on_startup_action,
+            queue_next_episodes_action,
+            resume_mode_action,
+            resume_playback_action,
+            start_over_action,
+            open_recent_action,
+            bookmark_add_action,
+            bookmark_next_action,
+            bookmark_previous_action,
+            bookmark_jump_action,
+            bookmark_rename_action,
+            bookmark_remove_action,
+            chapter_next_action,
+            chapter_previous_action,
+            ab_repeat_start_action,
+            ab_repeat_end_action,
+            ab_repeat_clear_action,
+            chapter_jump_action,
+            current_chapter: Cell::new(None),
             player_receiver: Some(player_receiver),
+            save_playlist_path: options.save_playlist.clone(),
+            session_path,
+            settings,
+            settings_save_source: RefCell::new(None),
+            incognito: options.incognito,
         })
     }
 
     pub fn quit(&self) {
         self.player.write_last_known_media_position();
+        self.player.write_cache();
+        if let Some(ref path) = self.save_playlist_path {
+            self.save_playlist(path);
+        }
+        self.save_session();
+        if let Some(source) = self.settings_save_source.take() {
+            source.remove();
+            self.save_settings();
+        }
         self.leave_fullscreen();
         self.ui_context.stop();
         println!("bye!")
     }
 
+    pub fn save_playlist(&self, path: &std::path::Path) {
+        if let Err(e) = self.player.save_playlist(path) {
+            eprintln!("Unable to save playlist to {}: {e}", path.display());
+        }
+    }
+
+    fn save_session(&self) {
+        let Some(ref path) = self.session_path else {
+            return;
+        };
+        let result = match self.player.session() {
+            Some(session) => session.write(path),
+            // Nothing to restore next time.
+            None if path.exists() => std::fs::remove_file(path).map_err(anyhow::Error::from),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            eprintln!("Unable to save session to {}: {e}", path.display());
+        }
+    }
+
+    pub fn restore_session(&self) {
+        let Some(ref path) = self.session_path else {
+            return;
+        };
+        match Session::read(path) {
+            Ok(session) => self.player.restore_session(session),
+            Err(e) => eprintln!("Unable to restore session from {}: {e}", path.display()),
+        }
+    }
+
+    // Only when launched without files, the playlist given on the command-line takes precedence.
+    fn restore_session_on_startup(&self) {
+        if !self.settings.restore_session || !self.player.get_playlist().0.is_empty() {
+            return;
+        }
+        if self.session_path.as_ref().is_some_and(|path| path.exists()) {
+            self.restore_session();
+        }
+    }
+
     pub fn start(&mut self) {
         let player_receiver = self.player_receiver.take().expect("No player channel receiver");
         glib::MainContext::default().spawn_local(async move {
@@ -445,7 +785,17 @@ impl VideoPlayer {
                     with_video_player!(video_player {
                         println!("loading {}", uri);
                         video_player.player.stop();
-                        video_player.player.load_uri(&uri);
+                        video_player.player.load_playlist(playlist_format::expand_uri(&uri));
+                    });
+                });
+            });
+        });
+
+        self.save_playlist_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.ui_context.save_dialog(&gettext("Save playlist"), "playlist.m3u8", |path| {
+                    with_video_player!(video_player {
+                        video_player.save_playlist(&path);
                     });
                 });
             });
@@ -468,6 +818,12 @@ impl VideoPlayer {
             });
         });
 
+        self.open_subtitle_style_window_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.ui_context.open_subtitle_style_window();
+            });
+        });
+
         self.show_shortcuts_action.connect_activate(|_, _| {
             with_video_player!(video_player {
                 video_player.ui_context.show_shortcuts();
@@ -510,6 +866,282 @@ impl VideoPlayer {
             });
         });
 
+        self.loop_action.connect_change_state(|action, value| {
+            if let Some(val) = value {
+                if let Some(loop_mode) = val
+                    .get::<std::string::String>()
+                    .and_then(|name| LoopMode::from_name(&name))
+                {
+                    with_video_player!(video_player {
+                        video_player.player.set_loop_mode(loop_mode);
+                        action.set_state(val);
+                    });
+                }
+            }
+        });
+
+        self.shuffle_action.connect_change_state(|action, value| {
+            if let Some(enabled) = value.and_then(|v| v.get::<bool>()) {
+                with_video_player!(video_player {
+                    video_player.player.set_shuffle(enabled);
+                    action.set_state(&enabled.to_variant());
+                });
+            }
+        });
+
+        self.crossfade_action.connect_change_state(|action, value| {
+            if let Some(seconds) = value.and_then(|v| v.get::<u32>()) {
+                with_video_player!(video_player {
+                    video_player
+                        .player
+                        .set_crossfade_duration(gst::ClockTime::from_seconds(seconds.into()));
+                    action.set_state(&seconds.to_variant());
+                });
+            }
+        });
+
+        self.restore_session_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.restore_session();
+            });
+        });
+
+        self.restore_session_on_startup_action
+            .connect_change_state(|action, value| {
+                if let Some(enabled) = value.and_then(|v| v.get::<bool>()) {
+                    with_mut_video_player!(video_player {
+                        video_player.settings.restore_session = enabled;
+                        video_player.save_settings();
+                        action.set_state(&enabled.to_variant());
+                    });
+                }
+            });
+
+        self.queue_next_episodes_action.connect_change_state(|action, value| {
+            if let Some(enabled) = value.and_then(|v| v.get::<bool>()) {
+                with_mut_video_player!(video_player {
+                    video_player.settings.queue_next_episodes = enabled;
+                    video_player.save_settings();
+                    action.set_state(&enabled.to_variant());
+                });
+            }
+        });
+
+        self.resume_mode_action.connect_change_state(|action, value| {
+            if let Some(mode) = value.and_then(|v| v.get::<std::string::String>()) {
+                let Some(mode) = ResumeMode::from_name(&mode) else {
+                    return;
+                };
+                with_mut_video_player!(video_player {
+                    video_player.player.set_resume_mode(mode);
+                    video_player.settings.resume = mode;
+                    video_player.save_settings();
+                    action.set_state(&mode.as_str().to_variant());
+                });
+            }
+        });
+
+        self.resume_playback_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.player.answer_resume(true);
+                video_player.ui_context.hide_resume_prompt();
+            });
+        });
+
+        self.start_over_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.player.answer_resume(false);
+                video_player.ui_context.hide_resume_prompt();
+            });
+        });
+
+        self.open_recent_action.connect_activate(|_, value| {
+            if let Some(uri) = value.and_then(|v| v.get::<std::string::String>()) {
+                with_mut_video_player!(video_player {
+                    video_player.open_uris(&[uri], OpenMode::Replace);
+                });
+            }
+        });
+
+        self.bookmark_add_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                // The moment to bookmark is the one at which it was asked for, not when the dialog is closed.
+                // Playback goes on meanwhile, possibly with the next media.
+                let (Some(uri), Some(position)) =
+                    (video_player.player.get_current_uri(), video_player.player.get_position())
+                else {
+                    return;
+                };
+                video_player.ui_context.bookmark_label_dialog(
+                    &gettext("Add Bookmark"),
+                    &gettext("Add"),
+                    "",
+                    move |label| {
+                        with_video_player!(video_player {
+                            video_player.player.add_bookmark(&uri, position, label);
+                            video_player.bookmarks_changed();
+                        });
+                    },
+                );
+            });
+        });
+
+        self.bookmark_next_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                let bookmarks = video_player.player.bookmarks();
+                if let Some(position) = video_player.player.get_position() {
+                    if let Some(bookmark) = bookmarks::next(&bookmarks, position) {
+                        video_player.player.seek_to(bookmark.position);
+                    }
+                }
+            });
+        });
+
+        self.bookmark_previous_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                let bookmarks = video_player.player.bookmarks();
+                if let Some(position) = video_player.player.get_position() {
+                    if let Some(bookmark) = bookmarks::previous(&bookmarks, position) {
+                        video_player.player.seek_to(bookmark.position);
+                    }
+                }
+            });
+        });
+
+        self.bookmark_jump_action.connect_activate(|_, value| {
+            if let Some((uri, position)) = value.and_then(|v| v.get::<(std::string::String, u64)>()) {
+                with_video_player!(video_player {
+                    if video_player.player.get_current_uri().as_deref() == Some(uri.as_str()) {
+                        video_player.player.seek_to(gst::ClockTime::from_nseconds(position));
+                    }
+                });
+            }
+        });
+
+        self.bookmark_rename_action.connect_activate(|_, value| {
+            if let Some((uri, position)) = value.and_then(|v| v.get::<(std::string::String, u64)>()) {
+                with_video_player!(video_player {
+                    let position = gst::ClockTime::from_nseconds(position);
+                    let Some(bookmark) = video_player
+                        .player
+                        .bookmarks_of(&uri)
+                        .into_iter()
+                        .find(|bookmark| bookmark.position == position)
+                    else {
+                        return;
+                    };
+                    video_player.ui_context.bookmark_label_dialog(
+                        &gettext("Rename Bookmark"),
+                        &gettext("Rename"),
+                        &bookmark.label.clone().unwrap_or_default(),
+                        move |label| {
+                            with_video_player!(video_player {
+                                video_player.player.rename_bookmark(&uri, &bookmark, label);
+                                video_player.bookmarks_changed();
+                            });
+                        },
+                    );
+                });
+            }
+        });
+
+        self.bookmark_remove_action.connect_activate(|_, value| {
+            if let Some((uri, position)) = value.and_then(|v| v.get::<(std::string::String, u64)>()) {
+                with_video_player!(video_player {
+                    video_player
+                        .player
+                        .remove_bookmark(&uri, gst::ClockTime::from_nseconds(position));
+                    video_player.bookmarks_changed();
+                });
+            }
+        });
+
+        self.chapter_next_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                let chapters = video_player.player.chapters();
+                if let Some(position) = video_player.player.get_position() {
+                    if let Some(chapter) = chapters::next(&chapters, position) {
+                        video_player.player.seek_to(chapter.start);
+                    }
+                }
+            });
+        });
+
+        self.chapter_previous_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                let chapters = video_player.player.chapters();
+                if let Some(position) = video_player.player.get_position() {
+                    if let Some(chapter) = chapters::previous(&chapters, position) {
+                        video_player.player.seek_to(chapter.start);
+                    }
+                }
+            });
+        });
+
+        self.ab_repeat_start_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.player.set_ab_repeat_start();
+            });
+        });
+
+        self.ab_repeat_end_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                if !video_player.player.set_ab_repeat_end() {
+                    video_player
+                        .ui_context
+                        .show_toast(&gettext("The end of the loop has to be after its start"));
+                }
+            });
+        });
+
+        self.ab_repeat_clear_action.connect_activate(|_, _| {
+            with_video_player!(video_player {
+                video_player.player.clear_ab_repeat();
+            });
+        });
+
+        self.chapter_jump_action.connect_activate(|_, value| {
+            if let Some(index) = value.and_then(|v| v.get::<u32>()) {
+                with_video_player!(video_player {
+                    if let Some(chapter) = video_player.player.chapters().get(index as usize) {
+                        video_player.player.seek_to(chapter.start);
+                    }
+                });
+            }
+        });
+
+        self.playlist_jump_action.connect_activate(|_, value| {
+            if let Some(index) = value.and_then(|v| v.get::<u32>()) {
+                with_video_player!(video_player {
+                    video_player.player.jump_to(index as usize);
+                });
+            }
+        });
+
+        self.playlist_remove_action.connect_activate(|_, value| {
+            if let Some(index) = value.and_then(|v| v.get::<u32>()) {
+                with_video_player!(video_player {
+                    video_player.player.remove_from_playlist(index as usize);
+                });
+            }
+        });
+
+        self.playlist_play_next_action.connect_activate(|_, value| {
+            if let Some(index) = value.and_then(|v| v.get::<u32>()) {
+                with_video_player!(video_player {
+                    video_player.player.play_next(index as usize);
+                });
+            }
+        });
+
+        self.playlist_move_action.connect_activate(|_, value| {
+            if let Some((from, to)) = value.and_then(|v| v.get::<(u32, u32)>()) {
+                with_video_player!(video_player {
+                    video_player.player.move_in_playlist(from as usize, to as usize);
+                });
+            }
+        });
+
         let paintable = self.player.paintable();
         paintable.connect_invalidate_size(|p| {
             with_video_player!(video_player {
@@ -543,7 +1175,7 @@ impl VideoPlayer {
                 }
                 println!("loading {}", uri);
                 video_player.player.stop();
-                video_player.player.load_uri(uri);
+                video_player.player.load_playlist(playlist_format::expand_uri(uri));
             })
         });
 
@@ -559,6 +1191,17 @@ impl VideoPlayer {
             })
         });
 
+        self.ui_context.set_subtitle_style_changed_callback(|style| {
+            with_mut_video_player!(video_player {
+                if video_player.settings.subtitle_style == style {
+                    return;
+                }
+                video_player.player.set_subtitle_style(&style);
+                video_player.settings.subtitle_style = style;
+                video_player.schedule_settings_save();
+            })
+        });
+
         self.ui_context.start(|| {
             with_video_player!(video_player {
                 video_player.quit();
@@ -567,14 +1210,19 @@ impl VideoPlayer {
     }
 
     pub fn configure(&self) {
+        self.refresh_recent_media();
         self.ui_context.set_progress_bar_format_callback(|value| {
             let position = gst::ClockTime::from_seconds(value as u64);
             with_optional_video_player!(video_player {
-                let status = if let Some(duration) = video_player.player.duration() {
+                let mut status = if let Some(duration) = video_player.player.duration() {
                     format!("{position:.0} / {duration:.0}")
                 } else {
                     format!("{position:.0}")
                 };
+                let chapters = video_player.player.chapters();
+                if let Some(index) = chapters::current(&chapters, position) {
+                    status = format!("{status} – {}", chapter_title(&chapters[index], index));
+                }
                 // FIXME: Ideally it'd be nice to show this on an OSD overlay?
                 let playback_rate = video_player.player.playback_rate();
                 if playback_rate != 1.0 {
@@ -620,6 +1268,35 @@ impl VideoPlayer {
             PlayerEvent::SeekDone => {
                 self.seek_done();
             }
+            PlayerEvent::LoopModeChanged(loop_mode) => {
+                self.loop_action.set_state(&loop_mode.as_str().to_variant());
+            }
+            PlayerEvent::ShuffleChanged(enabled) => {
+                self.shuffle_action.set_state(&enabled.to_variant());
+            }
+            PlayerEvent::PlaylistChanged => {
+                self.playlist_changed();
+            }
+            PlayerEvent::ResumeAvailable(position) => match position {
+                Some(position) => self.ui_context.show_resume_prompt(position),
+                None => self.ui_context.hide_resume_prompt(),
+            },
+            PlayerEvent::ChaptersChanged => {
+                self.chapters_changed();
+            }
+            PlayerEvent::AbRepeatChanged(a, b) => {
+                self.ab_repeat_clear_action.set_enabled(a.is_some());
+                self.ui_context
+                    .set_ab_repeat_range(a.map(|a| a.seconds_f64()), b.map(|b| b.seconds_f64()));
+            }
+            PlayerEvent::AbRepeatCancelled => {
+                self.ui_context
+                    .show_toast(&gettext("The A–B loop was cleared, playback moved out of it"));
+            }
+            PlayerEvent::CacheWriteFailed(message) => {
+                self.ui_context
+                    .show_toast(&gettext("Unable to save playback history: {}").replace("{}", &message));
+            }
             _ => {}
         };
     }
@@ -636,6 +1313,134 @@ impl VideoPlayer {
         self.ui_context.show_error_dialog(report_path, debug);
     }
 
+    pub fn playlist_changed(&self) {
+        let (items, index) = self.player.get_playlist();
+        let rows = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| PlaylistRow {
+                title: item.display_title(),
+                duration: item.duration,
+                current: i == index,
+            })
+            .collect();
+        self.ui_context.update_playlist(rows);
+        if items.is_empty() {
+            self.ui_context.show_welcome_page();
+        }
+    }
+
+    pub fn bookmarks_changed(&self) {
+        let bookmarks = self.player.bookmarks();
+        let uri = self.player.get_current_uri().unwrap_or_default();
+        let section = gio::Menu::new();
+        for bookmark in &bookmarks {
+            // The menu might be used after another media started, bookmarks are designated by value.
+            let target = (uri.to_string(), bookmark.position.nseconds()).to_variant();
+            let submenu = gio::Menu::new();
+            for (label, action) in [
+                (gettext("Go to"), "app.bookmark-jump"),
+                (gettext("Rename…"), "app.bookmark-rename"),
+                (gettext("Delete"), "app.bookmark-remove"),
+            ] {
+                let item = gio::MenuItem::new(Some(&label), None);
+                item.set_action_and_target_value(Some(action), Some(&target));
+                submenu.append_item(&item);
+            }
+            section.append_submenu(Some(&bookmark.display_label()), &submenu);
+        }
+        self.ui_context.update_bookmark_list_menu(&section);
+
+        self.update_progress_bar_marks();
+        self.bookmark_next_action.set_enabled(!bookmarks.is_empty());
+        self.bookmark_previous_action.set_enabled(!bookmarks.is_empty());
+    }
+
+    pub fn chapters_changed(&self) {
+        let chapters = self.player.chapters();
+        let section = gio::Menu::new();
+        for (i, chapter) in chapters.iter().enumerate() {
+            let label = format!("{:.0} {}", chapter.start, chapter_title(chapter, i));
+            let item = gio::MenuItem::new(Some(&label), None);
+            item.set_action_and_target_value(Some("app.chapter-jump"), Some(&(i as u32).to_variant()));
+            section.append_item(&item);
+        }
+        self.ui_context.update_chapter_list_menu(&section);
+
+        self.update_progress_bar_marks();
+        self.chapter_next_action.set_enabled(!chapters.is_empty());
+        self.chapter_previous_action.set_enabled(!chapters.is_empty());
+        self.current_chapter.set(None);
+        self.ui_context.set_window_subtitle("");
+        self.update_current_chapter();
+    }
+
+    // Show the title of the chapter being played in the window subtitle.
+    fn update_current_chapter(&self) {
+        let Some(position) = self.player.get_position() else {
+            return;
+        };
+        let chapters = self.player.chapters();
+        let current = chapters::current(&chapters, position);
+        if current == self.current_chapter.get() {
+            return;
+        }
+        self.current_chapter.set(current);
+        match current {
+            Some(index) => self
+                .ui_context
+                .set_window_subtitle(&chapter_title(&chapters[index], index)),
+            None => self.ui_context.set_window_subtitle(""),
+        }
+    }
+
+    fn update_progress_bar_marks(&self) {
+        let bookmarks: Vec<f64> = self
+            .player
+            .bookmarks()
+            .iter()
+            .map(|b| b.position.seconds_f64())
+            .collect();
+        let chapters: Vec<f64> = self
+            .player
+            .chapters()
+            .iter()
+            .map(|chapter| chapter.start.seconds_f64())
+            .collect();
+        self.ui_context.set_progress_bar_marks(&bookmarks, &chapters);
+    }
+
+    pub fn refresh_recent_media(&self) {
+        let rows = self
+            .player
+            .recent_media(constants::RECENT_MEDIA_LIMIT)
+            .into_iter()
+            .filter_map(|record| {
+                let finished = record.is_finished();
+                let mut item = PlaylistItem::new(record.uri?);
+                item.title = record.title;
+                let remaining = match (record.position, record.duration) {
+                    (Some(position), Some(duration)) if !finished => duration.checked_sub(position),
+                    _ => None,
+                };
+                let progress = match (record.position, record.duration) {
+                    _ if finished => 1.0,
+                    (Some(position), Some(duration)) if !duration.is_zero() => {
+                        position.nseconds() as f64 / duration.nseconds() as f64
+                    }
+                    _ => 0.0,
+                };
+                Some(RecentRow {
+                    title: item.display_title(),
+                    uri: item.uri,
+                    progress,
+                    remaining,
+                })
+            })
+            .collect();
+        self.ui_context.update_recent_media(rows);
+    }
+
     pub fn volume_changed(&self, volume: f64) {
         self.ui_context.volume_changed(volume);
     }
@@ -659,8 +1464,17 @@ impl VideoPlayer {
     pub fn media_info_updated(&self) {
         if let Some(info) = self.player.get_media_info() {
             if let Some(uri) = self.player.get_current_uri() {
+                self.ui_context.show_player_page();
+                if !self.incognito {
+                    gtk::RecentManager::default().add_item(&uri);
+                }
+                self.refresh_recent_media();
+                self.bookmarks_changed();
+
                 if let Some(title) = info.title() {
                     self.ui_context.set_window_title(&title);
+                } else if let Some(title) = self.player.get_current_item().and_then(|item| item.title) {
+                    self.ui_context.set_window_title(&title);
                 } else if let Ok((filename, _)) = glib::filename_from_uri(&uri) {
                     self.ui_context
                         .set_window_title(&filename.as_os_str().to_string_lossy());
@@ -672,16 +1486,29 @@ impl VideoPlayer {
                     self.ui_context.set_position_range_end(duration.seconds() as f64);
                 }
 
-                // Look for a matching subtitle file in same directory.
-                if let Ok((mut path, _)) = glib::filename_from_uri(&uri) {
-                    for extension in constants::SUB_FILE_EXTENSIONS.iter() {
-                        path.set_extension(extension);
-                        let subfile = path.as_path();
-                        if subfile.is_file() {
-                            if let Ok(suburi) = glib::filename_to_uri(subfile, None) {
-                                self.player
-                                    .configure_subtitle_track(Some(SubtitleTrack::External(suburi)));
-                                break;
+                let record = self.player.restore_track_selection(&info);
+                if let Some(ref record) = record {
+                    if let Some(idx) = record.audio_track {
+                        self.audio_track_action.set_state(&format!("audio-{idx}").to_variant());
+                    }
+                    if let Some(idx) = record.video_track {
+                        self.video_track_action.set_state(&format!("video-{idx}").to_variant());
+                    }
+                }
+                let subtitle_restored = record.is_some_and(|r| r.subtitle_track.is_some() || r.subtitle_uri.is_some());
+
+                // Look for a matching subtitle file in same directory, unless a subtitle was chosen before.
+                if !subtitle_restored {
+                    if let Ok((mut path, _)) = glib::filename_from_uri(&uri) {
+                        for extension in constants::SUB_FILE_EXTENSIONS.iter() {
+                            path.set_extension(extension);
+                            let subfile = path.as_path();
+                            if subfile.is_file() {
+                                if let Ok(suburi) = glib::filename_to_uri(subfile, None) {
+                                    self.player
+                                        .configure_subtitle_track(Some(SubtitleTrack::External(suburi)));
+                                    break;
+                                }
                             }
                         }
                     }
@@ -712,6 +1539,31 @@ impl VideoPlayer {
         if let Some(position) = self.player.get_position() {
             self.ui_context.set_position_range_value(position.seconds());
         }
+        self.update_current_chapter();
+    }
+
+    // Preferences changed in incognito mode only last until Glide is closed.
+    fn save_settings(&self) {
+        if self.incognito {
+            return;
+        }
+        if let Err(e) = self.settings.save() {
+            eprintln!("Unable to save settings: {e}");
+        }
+    }
+
+    // The subtitle style changes continuously while a slider is dragged, save it once it settles.
+    fn schedule_settings_save(&self) {
+        if let Some(source) = self.settings_save_source.take() {
+            source.remove();
+        }
+        let source = glib::timeout_add_local_once(std::time::Duration::from_secs(1), || {
+            with_video_player!(video_player {
+                video_player.settings_save_source.take();
+                video_player.save_settings();
+            })
+        });
+        self.settings_save_source.replace(Some(source));
     }
 
     pub fn seek_done(&self) {
@@ -863,26 +1715,23 @@ impl VideoPlayer {
         self.ui_context.update_video_track_menu(&section);
     }
 
-    pub fn open_files(&mut self, files: &[gio::File]) {
-        let mut playlist = vec![];
-        for file in files {
-            let uri = if let Some(_path) = file.path() {
-                Some(std::string::String::from(file.uri().as_str()))
-            } else {
-                // Gio built an invalid URI, so try to find the original CLI
-                // argument based on the URI scheme.
-                let uri_scheme = file.uri_scheme().unwrap();
-                let args = env::args().collect::<Vec<_>>();
-                let mut args_iter = args.iter();
-                let item = args_iter.find(|&i| i.starts_with(uri_scheme.as_str()));
-                item.map(std::string::String::from)
-            };
-            if let Some(uri) = uri {
-                playlist.push(uri);
+    pub fn open_uris(&mut self, uris: &[std::string::String], mode: OpenMode) {
+        let mut playlist: Vec<PlaylistItem> = uris.iter().flat_map(|uri| playlist_format::expand_uri(uri)).collect();
+        if mode == OpenMode::Replace && self.settings.queue_next_episodes {
+            // A single media file, neither a directory nor a playlist, might be part of a series.
+            if let [uri] = uris {
+                if playlist.len() == 1 && &playlist[0].uri == uri {
+                    if let Ok((path, _)) = glib::filename_from_uri(uri) {
+                        playlist.extend(episodes::following_siblings(&path).into_iter().map(PlaylistItem::new));
+                    }
+                }
             }
         }
-
-        self.player.load_playlist(playlist);
+        match mode {
+            OpenMode::Replace => self.player.load_playlist(playlist),
+            OpenMode::Enqueue => self.player.enqueue(playlist, false),
+            OpenMode::PlayNext => self.player.enqueue(playlist, true),
+        }
     }
 
     pub fn leave_fullscreen(&self) {
@@ -898,6 +1747,30 @@ impl VideoPlayer {
     }
 }
 
+// Arguments are relative to the working directory of the invocation, which can differ from the one of the
+// running instance.
+fn command_line_uri(command_line: &gio::ApplicationCommandLine, file: &std::path::Path) -> std::string::String {
+    let arg = file.to_string_lossy();
+    if glib::Uri::peek_scheme(&arg).is_some() {
+        return arg.to_string();
+    }
+    command_line.create_file_for_arg(arg.as_ref()).uri().into()
+}
+
+// Chapters without a title are numbered.
+fn chapter_title(chapter: &chapters::Chapter, index: usize) -> std::string::String {
+    match chapter.title {
+        Some(ref title) => title.clone(),
+        None => gettext("Chapter {}").replace("{}", &(index + 1).to_string()),
+    }
+}
+
+fn media_cache_path() -> anyhow::Result<PathBuf> {
+    let dirs =
+        ProjectDirs::from("net", "baseart", "Glide").ok_or(anyhow::anyhow!("Unable to determine cache directory"))?;
+    Ok(dirs.cache_dir().join("media-cache.json"))
+}
+
 fn main() -> anyhow::Result<()> {
     #[cfg(not(unix))]
     {
@@ -905,6 +1778,11 @@ fn main() -> anyhow::Result<()> {
     }
     i18n::init();
 
+    let opt = Opt::parse();
+    if let Some(Command::Cache(command)) = opt.command {
+        return cache_command::run(command, &media_cache_path()?);
+    }
+
     gst::init().expect("Failed to initialize GStreamer.");
     gtk::init().expect("Failed to initialize GTK.");
     gstgtk4::plugin_register_static().expect("Failed to register gstgtk4 plugin.");
@@ -914,21 +1792,22 @@ fn main() -> anyhow::Result<()> {
     let gtk_app = create_app();
 
     let gtk_app_clone = gtk_app.clone();
-    let opt = Opt::parse();
     let app = VideoPlayer::new(gtk_app, &opt)?;
 
     GLOBAL.with(move |global| {
         *global.borrow_mut() = Some(app);
     });
 
-    let files: Vec<std::string::String> = opt
-        .files
-        .iter()
-        .map(|p| std::string::String::from(p.to_str().unwrap()))
-        .collect();
+    // Options only read when the instance starts can't be handed to one that is already running.
+    gtk_app_clone.register(gio::Cancellable::NONE)?;
+    if gtk_app_clone.is_remote() && (opt.incognito || opt.save_playlist.is_some()) {
+        anyhow::bail!(
+            "--incognito and --save-playlist can't be applied to the running instance of Glide, quit it first"
+        );
+    }
 
-    let mut args = vec![env::args().next().unwrap()];
-    args.extend(files);
+    // The arguments are parsed again from the command-line handler, possibly in the already running instance.
+    let args: Vec<std::string::String> = env::args().collect();
     gtk_app_clone.run_with_args(&args);
 
     Ok(())
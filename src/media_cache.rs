@@ -0,0 +1,1046 @@
+use crate::bookmarks::Bookmark;
+use gstreamer::glib;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path;
+use std::string;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Version of the on-disk format, to bump when records can no longer be read by older releases.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Bounds of the cache, the least recently played media are forgotten first.
+pub const MAX_ENTRIES: usize = 2000;
+pub const MAX_AGE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+// Media shorter than this always play from the start.
+pub const RESUME_MIN_DURATION: gst::ClockTime = gst::ClockTime::from_seconds(120);
+// Positions closer than this to the start aren't worth resuming.
+pub const RESUME_MIN_POSITION: gst::ClockTime = gst::ClockTime::from_seconds(15);
+// Media stopped within this margin of the end, or past RESUME_END_PERCENT of their duration, count as finished,
+// credits are often skipped.
+pub const RESUME_END_MARGIN: gst::ClockTime = gst::ClockTime::from_seconds(30);
+pub const RESUME_END_PERCENT: u64 = 95;
+
+// Amount of data read at both ends of a file to compute its fingerprint.
+const FINGERPRINT_CHUNK_SIZE: u64 = 64 * 1024;
+
+// Covers younger than this might belong to a media whose record wasn't written yet.
+const COVER_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// What is known about a media that was played.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct MediaRecord {
+    pub uri: Option<string::String>,
+    /// Identifies local files by their contents, see [`fingerprint`].
+    pub fingerprint: Option<string::String>,
+    pub position: Option<gst::ClockTime>,
+    pub duration: Option<gst::ClockTime>,
+    /// Seconds since the Unix epoch.
+    pub last_played: Option<u64>,
+    pub title: Option<string::String>,
+    pub play_count: u32,
+    // Track selections and synchronization, as last chosen by the user. Track indices of -1 stand for
+    // disabled tracks.
+    pub audio_track: Option<i32>,
+    pub video_track: Option<i32>,
+    pub subtitle_track: Option<i32>,
+    pub subtitle_uri: Option<string::String>,
+    /// Nanoseconds.
+    pub audio_video_offset: Option<i64>,
+    /// Nanoseconds.
+    pub subtitle_video_offset: Option<i64>,
+    pub rate: Option<f64>,
+    /// File name of the cover art in the covers directory.
+    pub cover: Option<string::String>,
+    /// Sorted by position.
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl MediaRecord {
+    /// Whether playback was stopped close enough to the end.
+    pub fn is_finished(&self) -> bool {
+        let (Some(position), Some(duration)) = (self.position, self.duration) else {
+            return false;
+        };
+        position + RESUME_END_MARGIN >= duration
+            || position.nseconds() >= duration.nseconds() / 100 * RESUME_END_PERCENT
+    }
+
+    // Whether the media is a local file that still exists.
+    fn file_exists(&self) -> bool {
+        self.uri
+            .as_deref()
+            .and_then(|uri| glib::filename_from_uri(uri).ok())
+            .is_some_and(|(path, _)| path.exists())
+    }
+
+    /// Where playback should resume, if anywhere. The duration of media from older releases is only known
+    /// once they are played again, until then only the position is checked.
+    pub fn resume_position(&self) -> Option<gst::ClockTime> {
+        let position = self.position?;
+        if position < RESUME_MIN_POSITION
+            || self.duration.is_some_and(|duration| duration < RESUME_MIN_DURATION)
+            || self.is_finished()
+        {
+            return None;
+        }
+        Some(position)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct MediaCacheData {
+    version: u32,
+    media: HashMap<string::String, MediaRecord>,
+}
+
+/// Per media records, indexed by a hash of their URI.
+pub struct MediaCache {
+    path: path::PathBuf,
+    records: HashMap<string::String, MediaRecord>,
+    // Set when the file was written by a newer release, which would lose data if we replaced it.
+    read_only: bool,
+    // Records changed or removed since the cache was read or written, which take precedence over the
+    // ones written by other instances in the meantime.
+    changed: HashSet<string::String>,
+    removed: HashSet<string::String>,
+    // Fingerprints of local files by URI, computed with `local_fingerprint` away from the main thread.
+    fingerprints: HashMap<string::String, Option<string::String>>,
+}
+
+/// What was removed from the cache by [`MediaCache::prune`].
+#[derive(Debug, Default, PartialEq)]
+pub struct PruneStats {
+    pub missing: usize,
+    pub evicted: usize,
+    pub covers: usize,
+}
+
+impl MediaCache {
+    pub fn open<T: Into<path::PathBuf>>(path: T) -> Self {
+        let path = path.into();
+        match Self::read(&path) {
+            Ok(cache) => cache,
+            Err(e) => {
+                if path.exists() {
+                    eprintln!("Unable to read media cache {}: {e}", path.display());
+                }
+                Self::empty(path)
+            }
+        }
+    }
+
+    fn empty(path: path::PathBuf) -> Self {
+        Self {
+            path,
+            records: HashMap::new(),
+            read_only: false,
+            changed: HashSet::new(),
+            removed: HashSet::new(),
+            fingerprints: HashMap::new(),
+        }
+    }
+
+    pub fn read<T: Into<path::PathBuf>>(path: T) -> anyhow::Result<Self> {
+        let path = path.into();
+        let mut file = File::open(&path)?;
+        let mut data = string::String::new();
+        file.read_to_string(&mut data)?;
+        Self::parse(path, &data)
+    }
+
+    fn parse(path: path::PathBuf, data: &str) -> anyhow::Result<Self> {
+        let json: serde_json::Value = serde_json::from_str(data)?;
+        let Some(version) = json.get("version") else {
+            let records = migrate_v0(json, now())?;
+            // Rewritten in the current format on the next write.
+            let changed = records.keys().cloned().collect();
+            return Ok(Self {
+                records,
+                changed,
+                ..Self::empty(path)
+            });
+        };
+        let version = version.as_u64().ok_or(anyhow::anyhow!("Invalid media cache version"))?;
+        if version <= u64::from(SCHEMA_VERSION) {
+            let data: MediaCacheData = serde_json::from_value(json)?;
+            return Ok(Self {
+                records: data.media,
+                ..Self::empty(path)
+            });
+        }
+
+        // Salvage what can be understood from records of a newer format, unknown fields are ignored.
+        eprintln!(
+            "Media cache {} has version {version}, newer than {SCHEMA_VERSION}, it won't be updated",
+            path.display()
+        );
+        let records = match json.get("media").and_then(|media| media.as_object()) {
+            Some(media) => media
+                .iter()
+                .filter_map(|(id, record)| Some((id.clone(), serde_json::from_value(record.clone()).ok()?)))
+                .collect(),
+            None => HashMap::new(),
+        };
+        Ok(Self {
+            records,
+            read_only: true,
+            ..Self::empty(path)
+        })
+    }
+
+    /// Write the records to disk, if they changed since they were read or last written. Records written
+    /// by other instances since then are kept, unless they were changed here too.
+    pub fn write(&mut self) -> anyhow::Result<()> {
+        if self.read_only || (self.changed.is_empty() && self.removed.is_empty()) {
+            return Ok(());
+        }
+
+        // Serialize the read-merge-write sequence with other instances.
+        let mut lock_path = self.path.clone().into_os_string();
+        lock_path.push(".lock");
+        let lock = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        lock.lock()?;
+
+        let mut records = match Self::read(&self.path) {
+            Ok(on_disk) if on_disk.read_only => {
+                // A newer release took over the file, leave it alone from now on.
+                self.read_only = true;
+                return Ok(());
+            }
+            Ok(on_disk) => on_disk.records,
+            Err(e) => {
+                if self.path.exists() {
+                    eprintln!("Replacing unreadable media cache {}: {e}", self.path.display());
+                }
+                HashMap::new()
+            }
+        };
+        for id in &self.removed {
+            records.remove(id);
+        }
+        for id in &self.changed {
+            if let Some(record) = self.records.get(id) {
+                records.insert(id.clone(), record.clone());
+            }
+        }
+
+        let data = MediaCacheData {
+            version: SCHEMA_VERSION,
+            media: records,
+        };
+        write_json(&self.path, &data)?;
+
+        self.records = data.media;
+        self.changed.clear();
+        self.removed.clear();
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the file was written by a newer release, changes are then never written.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// All the records, most recently played first.
+    pub fn records(&self) -> Vec<&MediaRecord> {
+        let mut records: Vec<&MediaRecord> = self.records.values().collect();
+        records.sort_by(|a, b| b.last_played.cmp(&a.last_played).then_with(|| a.uri.cmp(&b.uri)));
+        records
+    }
+
+    /// The record of a media, looked up by URI first, then by contents for local files that were renamed
+    /// or moved since they were last played, once their fingerprint was given to [`MediaCache::set_fingerprint`].
+    pub fn record(&self, uri: &str) -> Option<&MediaRecord> {
+        self.records
+            .get(&uri_to_sha256(uri))
+            .or_else(|| self.records.get(&self.find_moved(uri)?))
+    }
+
+    // Id of a record with the same fingerprint as the given local file. The records of files that are gone
+    // are preferred over the ones of copies, then the most recently played.
+    fn find_moved(&self, uri: &str) -> Option<string::String> {
+        let fingerprint = self.fingerprints.get(uri)?.as_ref()?;
+        self.records
+            .iter()
+            .filter(|(_, record)| record.fingerprint.as_ref() == Some(fingerprint))
+            .max_by_key(|(_, record)| (!record.file_exists(), record.last_played))
+            .map(|(id, _)| id.clone())
+    }
+
+    pub fn update<F: FnOnce(&mut MediaRecord)>(&mut self, uri: &str, f: F) {
+        let id = uri_to_sha256(uri);
+        if !self.records.contains_key(&id) {
+            if let Some(moved_id) = self.find_moved(uri) {
+                let record = self.records[&moved_id].clone();
+                // Copies of a file share the history of the original, which stays where it is.
+                if !record.file_exists() {
+                    self.remove(moved_id);
+                }
+                self.records.insert(id.clone(), record);
+            }
+        }
+        let record = self.records.entry(id.clone()).or_default();
+        let previous = record.clone();
+        record.uri = Some(uri.into());
+        if record.fingerprint.is_none() {
+            record.fingerprint = self.fingerprints.get(uri).cloned().flatten();
+        }
+        f(record);
+        if *record != previous {
+            self.removed.remove(&id);
+            self.changed.insert(id);
+        }
+    }
+
+    /// Whether the fingerprint of a local file is needed, to find its record or to store it there.
+    pub fn needs_fingerprint(&self, uri: &str) -> bool {
+        !self.fingerprints.contains_key(uri)
+            && glib::filename_from_uri(uri).is_ok()
+            && self
+                .records
+                .get(&uri_to_sha256(uri))
+                .is_none_or(|record| record.fingerprint.is_none())
+    }
+
+    /// Remember the fingerprint of a local file, as computed by [`local_fingerprint`]. Reading the file
+    /// might be slow, on network shares for instance, so the cache never does it by itself.
+    pub fn set_fingerprint(&mut self, uri: &str, fingerprint: Option<string::String>) {
+        self.fingerprints.insert(uri.into(), fingerprint);
+        if self.records.contains_key(&uri_to_sha256(uri)) {
+            self.update(uri, |_| {});
+        }
+    }
+
+    /// Count a new playback of the media.
+    pub fn mark_played(&mut self, uri: &str) {
+        let now = now();
+        self.update(uri, |record| {
+            record.play_count += 1;
+            record.last_played = Some(now);
+        });
+    }
+
+    /// The `limit` most recently played media, most recent first.
+    pub fn recent(&self, limit: usize) -> Vec<&MediaRecord> {
+        self.records()
+            .into_iter()
+            .filter(|record| record.uri.is_some() && record.last_played.is_some())
+            .take(limit)
+            .collect()
+    }
+
+    /// Remove the record of a media. Returns it, if there was one.
+    pub fn forget(&mut self, uri: &str) -> Option<MediaRecord> {
+        let id = uri_to_sha256(uri);
+        let id = if self.records.contains_key(&id) {
+            id
+        } else {
+            self.find_moved(uri)?
+        };
+        self.remove(id)
+    }
+
+    /// Remove all the records, except the ones with bookmarks unless `bookmarks` is set. Returns the removed
+    /// records.
+    pub fn clear(&mut self, bookmarks: bool) -> Vec<MediaRecord> {
+        let ids: Vec<string::String> = self
+            .records
+            .iter()
+            .filter(|(_, record)| bookmarks || record.bookmarks.is_empty())
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.into_iter().filter_map(|id| self.remove(id)).collect()
+    }
+
+    /// Write all the records, in the format of the cache file.
+    pub fn export<W: io::Write>(&self, writer: W) -> anyhow::Result<()> {
+        let data = MediaCacheData {
+            version: SCHEMA_VERSION,
+            media: self.records.clone(),
+        };
+        serde_json::to_writer_pretty(writer, &data)?;
+        Ok(())
+    }
+
+    /// Merge records written by [`MediaCache::export`], or by any older release in its cache file. Records
+    /// played more recently here are kept. Returns how many records were added or replaced.
+    pub fn import<R: io::Read>(&mut self, mut reader: R) -> anyhow::Result<usize> {
+        let mut data = string::String::new();
+        reader.read_to_string(&mut data)?;
+        let imported = Self::parse(self.path.clone(), &data)?;
+        if imported.read_only {
+            anyhow::bail!("The media were exported by a newer release of Glide");
+        }
+        let mut count = 0;
+        for (id, record) in imported.records {
+            if self
+                .records
+                .get(&id)
+                .is_some_and(|existing| existing.last_played >= record.last_played)
+            {
+                continue;
+            }
+            self.records.insert(id.clone(), record);
+            self.removed.remove(&id);
+            self.changed.insert(id);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Forget the media not played for longer than `max_age` seconds, then the least recently played
+    /// ones until at most `max_entries` remain. Bookmarks were added on purpose, their media are always kept
+    /// and not counted. Media never marked as played have no age, they are the last ones forgotten to stay
+    /// within `max_entries`. Returns how many records were removed.
+    pub fn evict(&mut self, now: u64, max_entries: usize, max_age: u64) -> usize {
+        let oldest = now.saturating_sub(max_age);
+        let mut by_age: Vec<(bool, Option<u64>, string::String)> = self
+            .records
+            .iter()
+            .filter(|(_, record)| record.bookmarks.is_empty())
+            .map(|(id, record)| (record.last_played.is_none(), record.last_played, id.clone()))
+            .collect();
+        by_age.sort();
+        let excess = by_age.len().saturating_sub(max_entries);
+        let evicted: Vec<string::String> = by_age
+            .into_iter()
+            .enumerate()
+            .filter(|(i, (_, played, _))| *i < excess || played.is_some_and(|played| played < oldest))
+            .map(|(_, (_, _, id))| id)
+            .collect();
+        let count = evicted.len();
+        for id in evicted {
+            self.remove(id);
+        }
+        count
+    }
+
+    fn remove(&mut self, id: string::String) -> Option<MediaRecord> {
+        let record = self.records.remove(&id);
+        self.changed.remove(&id);
+        self.removed.insert(id);
+        record
+    }
+
+    /// Apply the default bounds of the cache.
+    pub fn evict_stale(&mut self) -> usize {
+        self.evict(now(), MAX_ENTRIES, MAX_AGE.as_secs())
+    }
+
+    /// Forget local files that were deleted. Files of directories that are missing as well are kept,
+    /// they might be on a removable drive that isn't mounted. So are the bookmarks, the file might just
+    /// have been moved.
+    pub fn prune_missing(&mut self) -> usize {
+        let missing: Vec<string::String> = self
+            .records
+            .iter()
+            .filter(|(_, record)| record.bookmarks.is_empty())
+            .filter(|(_, record)| {
+                let Some(Ok((path, _))) = record.uri.as_deref().map(glib::filename_from_uri) else {
+                    return false;
+                };
+                !path.exists() && path.parent().is_some_and(|parent| parent.is_dir())
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        let count = missing.len();
+        for id in missing {
+            self.remove(id);
+        }
+        count
+    }
+
+    /// Delete the covers of a directory that no record refers to.
+    pub fn collect_covers(&self, directory: &path::Path) -> anyhow::Result<usize> {
+        let referenced: HashSet<&str> = self
+            .records
+            .values()
+            .filter_map(|record| record.cover.as_deref())
+            .collect();
+        let mut removed = 0;
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            if referenced.contains(entry.file_name().to_string_lossy().as_ref()) {
+                continue;
+            }
+            let modified = entry.metadata()?.modified()?;
+            if modified.elapsed().is_ok_and(|age| age > COVER_GRACE_PERIOD) {
+                fs::remove_file(entry.path())?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Remove deleted files, stale records and unused covers, then write the cache.
+    pub fn prune(&mut self) -> anyhow::Result<PruneStats> {
+        let mut stats = PruneStats {
+            missing: self.prune_missing(),
+            evicted: self.evict_stale(),
+            ..Default::default()
+        };
+        self.write()?;
+        let covers = covers_dir();
+        if covers.is_dir() {
+            stats.covers = self.collect_covers(&covers)?;
+        }
+        Ok(stats)
+    }
+}
+
+/// Replace a file with the JSON representation of `value`. Readers see either the previous or the new contents,
+/// never a partially written file.
+pub fn write_json<T: serde::Serialize>(path: &path::Path, value: &T) -> anyhow::Result<()> {
+    let directory = path
+        .parent()
+        .ok_or(anyhow::anyhow!("Invalid path {}", path.display()))?;
+    let mut file = tempfile::NamedTempFile::new_in(directory)?;
+    serde_json::to_writer(&mut file, value)?;
+    file.as_file().sync_all()?;
+    file.persist(path)?;
+    Ok(())
+}
+
+/// Where the cover art of audio files is extracted, for MPRIS clients.
+pub fn covers_dir() -> path::PathBuf {
+    let mut dir = glib::user_cache_dir();
+    dir.push("glide");
+    dir.push("covers");
+    dir
+}
+
+/// Delete the covers of records that were removed from the cache. Covers that are already gone are
+/// ignored.
+pub fn remove_covers(directory: &path::Path, records: &[MediaRecord]) -> io::Result<()> {
+    for cover in records.iter().filter_map(|record| record.cover.as_deref()) {
+        match fs::remove_file(directory.join(cover)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Content based identity of a file: its size and the sums of the 64-bit words of its first and last 64
+/// KiB, as computed by OpenSubtitles. Empty and unreadable files have none.
+pub fn fingerprint(path: &path::Path) -> Option<string::String> {
+    let mut file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    if size == 0 {
+        return None;
+    }
+    let chunk_size = size.min(FINGERPRINT_CHUNK_SIZE);
+    let mut hash = size;
+    let mut chunk = vec![0u8; chunk_size as usize];
+    for offset in [0, size - chunk_size] {
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        file.read_exact(&mut chunk).ok()?;
+        for word in chunk.chunks(8) {
+            let mut bytes = [0u8; 8];
+            bytes[..word.len()].copy_from_slice(word);
+            hash = hash.wrapping_add(u64::from_le_bytes(bytes));
+        }
+    }
+    Some(format!("{hash:016x}"))
+}
+
+/// Fingerprint of a local file, from its URI.
+pub fn local_fingerprint(uri: &str) -> Option<string::String> {
+    let (path, _) = glib::filename_from_uri(uri).ok()?;
+    fingerprint(&path)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// The first format was a bare map of URI hashes to positions in nanoseconds. Records are considered as
+// played at the time of the migration, so that they age from there.
+fn migrate_v0(json: serde_json::Value, now: u64) -> anyhow::Result<HashMap<string::String, MediaRecord>> {
+    let positions: HashMap<string::String, u64> = serde_json::from_value(json)?;
+    Ok(positions
+        .into_iter()
+        .map(|(id, position)| {
+            let record = MediaRecord {
+                position: Some(gst::ClockTime::from_nseconds(position)),
+                last_played: Some(now),
+                ..Default::default()
+            };
+            (id, record)
+        })
+        .collect())
+}
+
+pub fn uri_to_sha256(uri: &str) -> string::String {
+    let mut sh = Sha256::new();
+    sh.update(uri.as_bytes());
+    sh.finalize()
+        .into_iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn migrate_from_position_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("media-cache.json");
+        let id = uri_to_sha256("file:///a.mkv");
+        fs::write(&path, format!(r#"{{"{id}": 42000000000}}"#)).unwrap();
+
+        let mut cache = MediaCache::read(&path).unwrap();
+        assert_eq!(
+            cache.record("file:///a.mkv").unwrap().position,
+            Some(gst::ClockTime::from_seconds(42))
+        );
+        assert_eq!(cache.record("file:///a.mkv").unwrap().play_count, 0);
+        // The duration isn't part of the first format, playback still resumes.
+        assert_eq!(
+            cache.record("file:///a.mkv").unwrap().resume_position(),
+            Some(gst::ClockTime::from_seconds(42))
+        );
+
+        cache.write().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["version"], SCHEMA_VERSION);
+        let cache = MediaCache::read(&path).unwrap();
+        assert_eq!(
+            cache.record("file:///a.mkv").unwrap().position,
+            Some(gst::ClockTime::from_seconds(42))
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("media-cache.json");
+        let mut cache = MediaCache::open(&path);
+        cache.mark_played("file:///a.mkv");
+        cache.mark_played("file:///a.mkv");
+        cache.update("file:///a.mkv", |record| {
+            record.title = Some("A".into());
+            record.duration = Some(gst::ClockTime::from_seconds(60));
+            record
+                .bookmarks
+                .push(Bookmark::new(gst::ClockTime::from_seconds(42), Some("Solo".into())));
+        });
+        cache.write().unwrap();
+
+        let cache = MediaCache::read(&path).unwrap();
+        let record = cache.record("file:///a.mkv").unwrap();
+        assert_eq!(record.uri.as_deref(), Some("file:///a.mkv"));
+        assert_eq!(record.play_count, 2);
+        assert_eq!(record.title.as_deref(), Some("A"));
+        assert_eq!(record.duration, Some(gst::ClockTime::from_seconds(60)));
+        assert!(record.last_played.is_some());
+        assert_eq!(
+            record.bookmarks,
+            [Bookmark::new(gst::ClockTime::from_seconds(42), Some("Solo".into()))]
+        );
+        assert!(cache.record("file:///b.mkv").is_none());
+    }
+
+    #[test]
+    fn future_version_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("media-cache.json");
+        let id = uri_to_sha256("file:///a.mkv");
+        let contents = format!(
+            r#"{{"version": 99, "media": {{"{id}": {{"position": 5, "rating": 4}}, "x": "garbage"}}, "other": []}}"#
+        );
+        fs::write(&path, &contents).unwrap();
+
+        let mut cache = MediaCache::read(&path).unwrap();
+        assert_eq!(
+            cache.record("file:///a.mkv").unwrap().position,
+            Some(gst::ClockTime::from_nseconds(5))
+        );
+        cache.mark_played("file:///a.mkv");
+        cache.write().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+    }
+
+    #[test]
+    fn invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("media-cache.json");
+        fs::write(&path, "[1, 2]").unwrap();
+        assert!(MediaCache::read(&path).is_err());
+        assert!(MediaCache::open(&path).record("file:///a.mkv").is_none());
+    }
+
+    fn played_at(cache: &mut MediaCache, uri: &str, time: u64) {
+        cache.update(uri, |record| record.last_played = Some(time));
+    }
+
+    #[test]
+    fn evict_least_recently_played() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open(dir.path().join("media-cache.json"));
+        for (i, uri) in ["file:///a", "file:///b", "file:///c", "file:///d"].iter().enumerate() {
+            played_at(&mut cache, uri, 1000 + i as u64);
+        }
+        // Played long ago, but more recently than the others.
+        played_at(&mut cache, "file:///a", 2000);
+
+        assert_eq!(cache.evict(2000, 10, 5000), 0);
+        assert_eq!(cache.evict(2000, 2, 5000), 2);
+        assert!(cache.record("file:///a").is_some());
+        assert!(cache.record("file:///d").is_some());
+        assert!(cache.record("file:///b").is_none());
+        assert!(cache.record("file:///c").is_none());
+
+        // Bookmarked media are neither evicted nor counted.
+        cache.update("file:///bookmarked", |record| {
+            record.last_played = Some(10);
+            record
+                .bookmarks
+                .push(Bookmark::new(gst::ClockTime::from_seconds(1), None));
+        });
+        assert_eq!(cache.evict(2000, 2, 5000), 0);
+        assert_eq!(cache.evict(100_000, 2, 5000), 2);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.record("file:///bookmarked").is_some());
+    }
+
+    #[test]
+    fn evict_by_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open(dir.path().join("media-cache.json"));
+        played_at(&mut cache, "file:///old", 100);
+        played_at(&mut cache, "file:///recent", 950);
+        // Never played, for instance only a track selection was stored.
+        cache.update("file:///unknown", |record| record.audio_track = Some(1));
+
+        assert_eq!(cache.evict(1000, 10, 100), 1);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.record("file:///recent").is_some());
+        assert!(cache.record("file:///unknown").is_some());
+        // Media with a known age go first.
+        assert_eq!(cache.evict(1000, 1, 100), 1);
+        assert!(cache.record("file:///unknown").is_some());
+        assert_eq!(cache.evict(1000, 0, 100), 1);
+        assert_eq!(cache.len(), 0);
+
+        // Records migrated from the first format age from the migration on.
+        let id = uri_to_sha256("file:///a.mkv");
+        let path = dir.path().join("v0.json");
+        fs::write(&path, format!(r#"{{"{id}": 1}}"#)).unwrap();
+        let mut cache = MediaCache::read(&path).unwrap();
+        assert_eq!(cache.evict_stale(), 0);
+        assert_eq!(
+            cache.evict(now() + MAX_AGE.as_secs() + 1, MAX_ENTRIES, MAX_AGE.as_secs()),
+            1
+        );
+    }
+
+    #[test]
+    fn fingerprints() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let large: Vec<u8> = (0..200 * 1024).map(|i| (i % 251) as u8).collect();
+        fs::write(root.join("a.mkv"), &large).unwrap();
+        fs::write(root.join("copy.mkv"), &large).unwrap();
+        let mut changed = large.clone();
+        // Past the last chunk.
+        changed[100 * 1024] = 0xff;
+        fs::write(root.join("middle.mkv"), &changed).unwrap();
+        changed[large.len() - 1] = 0;
+        fs::write(root.join("end.mkv"), &changed).unwrap();
+        fs::write(root.join("small.mkv"), b"abc").unwrap();
+        fs::write(root.join("empty.mkv"), b"").unwrap();
+
+        let fingerprint = |name: &str| fingerprint(&root.join(name));
+        assert!(fingerprint("a.mkv").is_some());
+        assert_eq!(fingerprint("a.mkv"), fingerprint("copy.mkv"));
+        // Only both ends of the file are read.
+        assert_eq!(fingerprint("a.mkv"), fingerprint("middle.mkv"));
+        assert_ne!(fingerprint("a.mkv"), fingerprint("end.mkv"));
+        // Size 3, plus "abc" read twice.
+        assert_eq!(fingerprint("small.mkv"), Some(format!("{:016x}", 3 + 2 * 0x636261)));
+        assert_eq!(fingerprint("empty.mkv"), None);
+        assert_eq!(fingerprint("missing.mkv"), None);
+    }
+
+    #[test]
+    fn find_moved_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let uri = |name: &str| glib::filename_to_uri(root.join(name), None).unwrap().to_string();
+        fs::write(root.join("episode.mkv"), [1u8; 1024]).unwrap();
+        fs::write(root.join("other.mkv"), [2u8; 1024]).unwrap();
+
+        let mut cache = MediaCache::open(root.join("media-cache.json"));
+        let fingerprinted = |cache: &mut MediaCache, name: &str| {
+            assert!(cache.needs_fingerprint(&uri(name)));
+            cache.set_fingerprint(&uri(name), local_fingerprint(&uri(name)));
+        };
+        fingerprinted(&mut cache, "episode.mkv");
+        cache.update(&uri("episode.mkv"), |record| {
+            record.position = Some(gst::ClockTime::from_seconds(42))
+        });
+        cache.mark_played("https://example.com/stream.mkv");
+        assert!(cache.record(&uri("episode.mkv")).unwrap().fingerprint.is_some());
+        assert!(!cache.needs_fingerprint(&uri("episode.mkv")));
+        assert!(!cache.needs_fingerprint("https://example.com/stream.mkv"));
+        assert!(cache
+            .record("https://example.com/stream.mkv")
+            .unwrap()
+            .fingerprint
+            .is_none());
+
+        // A copy gets the history of the original, which keeps its own.
+        fs::copy(root.join("episode.mkv"), root.join("copy.mkv")).unwrap();
+        // Not read until its fingerprint is known.
+        assert!(cache.record(&uri("copy.mkv")).is_none());
+        fingerprinted(&mut cache, "copy.mkv");
+        assert_eq!(
+            cache.record(&uri("copy.mkv")).unwrap().position,
+            Some(gst::ClockTime::from_seconds(42))
+        );
+        cache.mark_played(&uri("copy.mkv"));
+        assert_eq!(cache.len(), 3);
+        fingerprinted(&mut cache, "other.mkv");
+        assert!(cache.record(&uri("other.mkv")).is_none());
+
+        // The record of a renamed file follows it.
+        fs::rename(root.join("episode.mkv"), root.join("S01E01.mkv")).unwrap();
+        fingerprinted(&mut cache, "S01E01.mkv");
+        let record = cache.record(&uri("S01E01.mkv")).unwrap();
+        assert_eq!(record.position, Some(gst::ClockTime::from_seconds(42)));
+        cache.update(&uri("S01E01.mkv"), |record| record.play_count += 1);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.record(&uri("S01E01.mkv")).unwrap().uri, Some(uri("S01E01.mkv")));
+
+        cache.write().unwrap();
+        let cache = MediaCache::read(root.join("media-cache.json")).unwrap();
+        assert_eq!(
+            cache.record(&uri("S01E01.mkv")).unwrap().position,
+            Some(gst::ClockTime::from_seconds(42))
+        );
+    }
+
+    #[test]
+    fn forget_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("media-cache.json");
+        let mut cache = MediaCache::open(&path);
+        cache.mark_played("file:///a");
+        cache.mark_played("file:///b");
+        cache.write().unwrap();
+
+        assert!(cache.forget("file:///a").is_some());
+        assert!(cache.forget("file:///a").is_none());
+        cache.write().unwrap();
+        let mut cache = MediaCache::read(&path).unwrap();
+        assert!(cache.record("file:///a").is_none());
+        assert_eq!(cache.len(), 1);
+
+        cache.update("file:///c", |record| {
+            record
+                .bookmarks
+                .push(Bookmark::new(gst::ClockTime::from_seconds(1), None))
+        });
+        assert_eq!(cache.clear(false).len(), 1);
+        assert!(cache.record("file:///c").is_some());
+        assert_eq!(cache.clear(true).len(), 1);
+        cache.write().unwrap();
+        assert_eq!(MediaCache::read(&path).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn remove_covers_of_forgotten_media() {
+        let dir = tempfile::tempdir().unwrap();
+        let covers = dir.path().join("covers");
+        fs::create_dir(&covers).unwrap();
+        for name in ["a", "b"] {
+            File::create(covers.join(name)).unwrap();
+        }
+
+        let mut cache = MediaCache::open(dir.path().join("media-cache.json"));
+        for name in ["a", "b", "gone"] {
+            cache.update(&format!("file:///{name}.ogg"), |record| {
+                record.cover = Some(name.into())
+            });
+        }
+        let forgotten = cache.forget("file:///a.ogg").unwrap();
+        remove_covers(&covers, &[forgotten]).unwrap();
+        assert!(!covers.join("a").exists());
+        assert!(covers.join("b").exists());
+
+        remove_covers(&covers, &cache.clear(false)).unwrap();
+        assert!(!covers.join("b").exists());
+    }
+
+    #[test]
+    fn export_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open(dir.path().join("media-cache.json"));
+        played_at(&mut cache, "file:///a", 100);
+        played_at(&mut cache, "file:///b", 100);
+        let mut exported = vec![];
+        cache.export(&mut exported).unwrap();
+
+        let mut other = MediaCache::open(dir.path().join("other.json"));
+        played_at(&mut other, "file:///b", 200);
+        played_at(&mut other, "file:///c", 200);
+        assert_eq!(other.import(exported.as_slice()).unwrap(), 1);
+        assert_eq!(other.len(), 3);
+        assert_eq!(other.record("file:///a").unwrap().last_played, Some(100));
+        // Played more recently on this machine.
+        assert_eq!(other.record("file:///b").unwrap().last_played, Some(200));
+
+        other.write().unwrap();
+        assert_eq!(MediaCache::read(dir.path().join("other.json")).unwrap().len(), 3);
+        assert!(other.import(&b"not json"[..]).is_err());
+    }
+
+    #[test]
+    fn recently_played() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = MediaCache::open(dir.path().join("media-cache.json"));
+        played_at(&mut cache, "file:///a", 100);
+        played_at(&mut cache, "file:///b", 300);
+        played_at(&mut cache, "file:///c", 200);
+        cache.update("file:///never-played", |record| record.audio_track = Some(1));
+
+        let uris: Vec<_> = cache
+            .recent(10)
+            .iter()
+            .map(|record| record.uri.as_deref().unwrap())
+            .collect();
+        assert_eq!(uris, ["file:///b", "file:///c", "file:///a"]);
+        assert_eq!(cache.recent(1).len(), 1);
+    }
+
+    #[test]
+    fn prune_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("kept.mkv"), "").unwrap();
+        let uri = |name: &str| glib::filename_to_uri(root.join(name), None).unwrap().to_string();
+
+        let mut cache = MediaCache::open(root.join("media-cache.json"));
+        cache.mark_played(&uri("kept.mkv"));
+        cache.mark_played(&uri("deleted.mkv"));
+        cache.mark_played(&uri("unmounted/file.mkv"));
+        cache.mark_played("https://example.com/stream.mkv");
+        cache.update(&uri("bookmarked.mkv"), |record| {
+            record
+                .bookmarks
+                .push(Bookmark::new(gst::ClockTime::from_seconds(1), None))
+        });
+
+        assert_eq!(cache.prune_missing(), 1);
+        assert!(cache.record(&uri("deleted.mkv")).is_none());
+        assert!(cache.record(&uri("bookmarked.mkv")).is_some());
+        assert_eq!(cache.len(), 4);
+    }
+
+    #[test]
+    fn collect_unreferenced_covers() {
+        let dir = tempfile::tempdir().unwrap();
+        let covers = dir.path().join("covers");
+        fs::create_dir(&covers).unwrap();
+        let old = SystemTime::now() - 2 * COVER_GRACE_PERIOD;
+        for name in ["used", "unused", "fresh"] {
+            let file = File::create(covers.join(name)).unwrap();
+            if name != "fresh" {
+                file.set_modified(old).unwrap();
+            }
+        }
+
+        let mut cache = MediaCache::open(dir.path().join("media-cache.json"));
+        cache.update("file:///a.ogg", |record| record.cover = Some("used".into()));
+        assert_eq!(cache.collect_covers(&covers).unwrap(), 1);
+        assert!(covers.join("used").exists());
+        assert!(!covers.join("unused").exists());
+        assert!(covers.join("fresh").exists());
+    }
+
+    #[test]
+    fn merge_with_other_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("media-cache.json");
+        let mut first = MediaCache::open(&path);
+        first.mark_played("file:///a");
+        first.mark_played("file:///b");
+        first.write().unwrap();
+
+        let mut second = MediaCache::open(&path);
+        first.update("file:///a", |record| record.rate = Some(1.5));
+        first.update("file:///c", |record| record.rate = Some(2.0));
+        first.write().unwrap();
+
+        second.update("file:///a", |record| record.audio_track = Some(1));
+        played_at(&mut second, "file:///d", 100);
+        assert_eq!(second.evict(now(), 10, 1000), 1);
+        second.write().unwrap();
+
+        let merged = MediaCache::read(&path).unwrap();
+        // Records changed by both instances are replaced as a whole by the last writer.
+        assert_eq!(merged.record("file:///a").unwrap().rate, None);
+        assert_eq!(merged.record("file:///a").unwrap().audio_track, Some(1));
+        assert_eq!(merged.record("file:///c").unwrap().rate, Some(2.0));
+        assert!(merged.record("file:///b").is_some());
+        // Played long ago, evicted by the second instance.
+        assert!(merged.record("file:///d").is_none());
+        assert_eq!(second.len(), 3);
+        assert!(!dir.path().read_dir().unwrap().any(|entry| {
+            let name = entry.unwrap().file_name();
+            name.to_string_lossy().starts_with(".tmp")
+        }));
+    }
+
+    #[test]
+    fn resume_thresholds() {
+        let record = |position: u64, duration: u64| MediaRecord {
+            position: Some(gst::ClockTime::from_seconds(position)),
+            duration: Some(gst::ClockTime::from_seconds(duration)),
+            ..Default::default()
+        };
+        let seconds = |s| Some(gst::ClockTime::from_seconds(s));
+
+        assert_eq!(record(2530, 5400).resume_position(), seconds(2530));
+        // Near the start.
+        assert_eq!(record(10, 5400).resume_position(), None);
+        assert_eq!(record(15, 5400).resume_position(), seconds(15));
+        // Near the end, in the last seconds or in the credits.
+        assert_eq!(record(5397, 5400).resume_position(), None);
+        assert!(record(5397, 5400).is_finished());
+        assert_eq!(record(5200, 5400).resume_position(), None);
+        assert_eq!(record(5000, 5400).resume_position(), seconds(5000));
+        assert_eq!(record(250, 300).resume_position(), seconds(250));
+        assert_eq!(record(271, 300).resume_position(), None);
+        // Short clips.
+        assert_eq!(record(60, 90).resume_position(), None);
+        // Unknown duration, only the start threshold applies.
+        let mut unknown = record(2530, 0);
+        unknown.duration = None;
+        assert_eq!(unknown.resume_position(), seconds(2530));
+        assert!(!unknown.is_finished());
+        unknown.position = Some(gst::ClockTime::from_seconds(10));
+        assert_eq!(unknown.resume_position(), None);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::playlist_format::PlaylistFormat;
+use gio::prelude::*;
+use std::cmp::Ordering;
+use std::path;
+use std::string;
+
+// How deep sub-directories are explored, enough for "Show/Season 1/Extras" like layouts.
+pub const MAX_DEPTH: usize = 4;
+
+/// Recursively list the audio and video files of a directory, in natural order. Entries of
+/// sub-directories are listed in place of the directory itself.
+pub fn expand(path: &path::Path, max_depth: usize) -> Vec<string::String> {
+    let mut uris = vec![];
+    collect(&gio::File::for_path(path), max_depth, &mut uris);
+    uris
+}
+
+fn collect(directory: &gio::File, depth: usize, uris: &mut Vec<string::String>) {
+    let attributes = format!(
+        "{},{},{},{}",
+        gio::FILE_ATTRIBUTE_STANDARD_NAME,
+        gio::FILE_ATTRIBUTE_STANDARD_TYPE,
+        gio::FILE_ATTRIBUTE_STANDARD_IS_HIDDEN,
+        gio::FILE_ATTRIBUTE_STANDARD_FAST_CONTENT_TYPE
+    );
+    let enumerator =
+        match directory.enumerate_children(&attributes, gio::FileQueryInfoFlags::NONE, gio::Cancellable::NONE) {
+            Ok(enumerator) => enumerator,
+            Err(e) => {
+                eprintln!("Unable to list {}: {e}", directory.uri());
+                return;
+            }
+        };
+
+    let mut entries: Vec<gio::FileInfo> = enumerator
+        .filter_map(Result::ok)
+        .filter(|info| !info.is_hidden())
+        .collect();
+    entries.sort_by(|a, b| natural_cmp(&a.name().to_string_lossy(), &b.name().to_string_lossy()));
+
+    for info in entries {
+        let child = directory.child(info.name());
+        match info.file_type() {
+            gio::FileType::Directory if depth > 0 => collect(&child, depth - 1, uris),
+            gio::FileType::Regular if is_media(&info) => uris.push(child.uri().into()),
+            _ => {}
+        }
+    }
+}
+
+fn is_media(info: &gio::FileInfo) -> bool {
+    // Playlists have audio MIME types too, but would be played along with their own entries.
+    if PlaylistFormat::from_path(&info.name()).is_some() {
+        return false;
+    }
+    let Some(content_type) = info.attribute_string(gio::FILE_ATTRIBUTE_STANDARD_FAST_CONTENT_TYPE) else {
+        return false;
+    };
+    let Some(mime_type) = gio::content_type_get_mime_type(&content_type) else {
+        return false;
+    };
+    mime_type.starts_with("video/") || mime_type.starts_with("audio/")
+}
+
+/// Whether a media is an audio file, guessed from its name. Streams without a known extension aren't.
+pub fn is_audio(uri: &str) -> bool {
+    let (content_type, _) = gio::content_type_guess(Some(uri), None);
+    gio::content_type_get_mime_type(&content_type).is_some_and(|mime_type| mime_type.starts_with("audio/"))
+}
+
+/// Compare strings the way humans do, numbers included: "ep2" sorts before "ep10".
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chunks = chunks(a);
+    let mut b_chunks = chunks(b);
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.as_bytes()[0].is_ascii_digit(), b.as_bytes()[0].is_ascii_digit()) {
+                (true, true) => {
+                    let a = a.trim_start_matches('0');
+                    let b = b.trim_start_matches('0');
+                    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+                }
+                _ => a.to_lowercase().cmp(&b.to_lowercase()),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+// Split a string in alternating runs of ASCII digits and non-digits.
+fn chunks(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != first.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (chunk, remainder) = rest.split_at(end);
+        rest = remainder;
+        Some(chunk)
+    })
+}
+
+// Create files to be listed under `root`, sub-directories included. GIO guesses the type of files from their
+// extension, except for empty files which are always reported as plain text, so they get some content.
+#[cfg(test)]
+pub(crate) fn create_files(root: &path::Path, names: &[&str]) {
+    for name in names {
+        let path = root.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, [0u8; 16]).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec![
+            "ep10.mkv",
+            "ep2.mkv",
+            "Ep1.mkv",
+            "ep02b.mkv",
+            "ep1.mkv",
+            "bonus.mkv",
+            "ep001.mkv",
+            "ep9.mkv",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "bonus.mkv",
+                "Ep1.mkv",
+                "ep001.mkv",
+                "ep1.mkv",
+                "ep2.mkv",
+                "ep02b.mkv",
+                "ep9.mkv",
+                "ep10.mkv"
+            ]
+        );
+    }
+
+    #[test]
+    fn natural_order_mixed() {
+        assert_eq!(natural_cmp("Season 2", "Season 10"), Ordering::Less);
+        assert_eq!(natural_cmp("track 3 - b", "track 3 - a"), Ordering::Greater);
+        assert_eq!(natural_cmp("10", "9a"), Ordering::Greater);
+        assert_eq!(natural_cmp("", "a"), Ordering::Less);
+        assert_eq!(natural_cmp("été 2", "été 12"), Ordering::Less);
+    }
+
+    fn names(uris: &[string::String], root: &path::Path) -> Vec<string::String> {
+        uris.iter()
+            .map(|uri| {
+                let (path, _) = gio::glib::filename_from_uri(uri).unwrap();
+                path.strip_prefix(root).unwrap().to_string_lossy().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn expand_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        create_files(
+            root,
+            &[
+                "Season 10/ep1.mkv",
+                "Season 2/ep10.mkv",
+                "Season 2/ep2.mkv",
+                "Season 2/notes.txt",
+                "Season 2/ep2.srt",
+                "Season 2/.hidden.mkv",
+                "Season 2/Extras/Deep/Deeper/making-of.mkv",
+                "intro.ogg",
+                "list.m3u",
+            ],
+        );
+
+        assert_eq!(
+            names(&expand(root, MAX_DEPTH), root),
+            [
+                "intro.ogg",
+                "Season 2/ep2.mkv",
+                "Season 2/ep10.mkv",
+                "Season 2/Extras/Deep/Deeper/making-of.mkv",
+                "Season 10/ep1.mkv"
+            ]
+        );
+        assert_eq!(
+            names(&expand(root, 1), root),
+            [
+                "intro.ogg",
+                "Season 2/ep2.mkv",
+                "Season 2/ep10.mkv",
+                "Season 10/ep1.mkv"
+            ]
+        );
+        assert_eq!(names(&expand(root, 0), root), ["intro.ogg"]);
+    }
+
+    #[test]
+    fn audio_guessed_from_name() {
+        assert!(is_audio("file:///music/01%20intro.flac"));
+        assert!(is_audio("file:///music/track.ogg"));
+        assert!(!is_audio("file:///videos/ep1.mkv"));
+        assert!(!is_audio("file:///videos/ep1.ogv"));
+        assert!(!is_audio("https://example.com/live"));
+    }
+}
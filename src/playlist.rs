@@ -0,0 +1,758 @@
+use gstreamer::glib;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::string;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlaylistItem {
+    // Identifier assigned by the playlist, stable while the item stays in it.
+    #[serde(skip)]
+    pub id: u64,
+    pub uri: string::String,
+    pub title: Option<string::String>,
+    pub duration: Option<gst::ClockTime>,
+}
+
+impl PlaylistItem {
+    pub fn new<T: Into<string::String>>(uri: T) -> Self {
+        Self {
+            id: 0,
+            uri: uri.into(),
+            title: None,
+            duration: None,
+        }
+    }
+
+    pub fn display_title(&self) -> string::String {
+        if let Some(ref title) = self.title {
+            return title.clone();
+        }
+        if let Ok((path, _)) = glib::filename_from_uri(&self.uri) {
+            if let Some(name) = path.file_name() {
+                return name.to_string_lossy().to_string();
+            }
+        }
+        self.uri.clone()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum LoopMode {
+    #[default]
+    None,
+    Track,
+    Playlist,
+}
+
+impl LoopMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Track => "track",
+            Self::Playlist => "playlist",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "track" => Some(Self::Track),
+            "playlist" => Some(Self::Playlist),
+            _ => None,
+        }
+    }
+}
+
+pub struct Playlist {
+    items: Vec<PlaylistItem>,
+    index: usize,
+    loop_mode: LoopMode,
+    // Play order when shuffling, as indices in `items`. The original order is left untouched.
+    shuffle_order: Option<Vec<usize>>,
+    rng: StdRng,
+    next_id: u64,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Playlist {
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self {
+            items: vec![],
+            index: 0,
+            loop_mode: LoopMode::None,
+            shuffle_order: None,
+            rng,
+            next_id: 1,
+        }
+    }
+
+    /// Seed the random play order. A playlist already shuffled is shuffled again, from the current item on.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        if self.is_shuffled() {
+            self.shuffle_order = Some(self.shuffled_order(Some(self.index)));
+        }
+    }
+
+    fn assign_id(&mut self, item: &mut PlaylistItem) {
+        item.id = self.next_id;
+        self.next_id += 1;
+    }
+
+    pub fn set_items(&mut self, mut items: Vec<PlaylistItem>) {
+        for item in items.iter_mut() {
+            self.assign_id(item);
+        }
+        self.items = items;
+        self.index = 0;
+        if self.shuffle_order.is_some() {
+            let order = self.shuffled_order(None);
+            self.index = order.first().copied().unwrap_or(0);
+            self.shuffle_order = Some(order);
+        }
+    }
+
+    pub fn items(&self) -> &[PlaylistItem] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn position_of(&self, id: u64) -> Option<usize> {
+        self.items.iter().position(|item| item.id == id)
+    }
+
+    pub fn current(&self) -> Option<&PlaylistItem> {
+        self.items.get(self.index)
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut PlaylistItem> {
+        self.items.get_mut(self.index)
+    }
+
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        self.loop_mode = loop_mode;
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle_order.is_some()
+    }
+
+    pub fn set_shuffle(&mut self, enabled: bool) {
+        if enabled == self.is_shuffled() {
+            return;
+        }
+        self.shuffle_order = if enabled {
+            Some(self.shuffled_order(Some(self.index)))
+        } else {
+            None
+        };
+    }
+
+    fn shuffled_order(&mut self, first: Option<usize>) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.shuffle(&mut self.rng);
+        if let Some(position) = first.and_then(|first| order.iter().position(|&i| i == first)) {
+            let first = order.remove(position);
+            order.insert(0, first);
+        }
+        order
+    }
+
+    // Position of the current item in the play order.
+    fn position(&self) -> usize {
+        match self.shuffle_order {
+            Some(ref order) => order.iter().position(|&i| i == self.index).unwrap_or(0),
+            None => self.index,
+        }
+    }
+
+    fn index_at(&self, position: usize) -> usize {
+        match self.shuffle_order {
+            Some(ref order) => order[position],
+            None => position,
+        }
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        let position = self.position();
+        if position + 1 < self.len() {
+            Some(self.index_at(position + 1))
+        } else if self.loop_mode == LoopMode::Playlist && !self.is_empty() {
+            Some(self.index_at(0))
+        } else {
+            None
+        }
+    }
+
+    fn previous_index(&self) -> Option<usize> {
+        let position = self.position();
+        if position >= 1 {
+            Some(self.index_at(position - 1))
+        } else if self.loop_mode == LoopMode::Playlist && !self.is_empty() {
+            Some(self.index_at(self.len() - 1))
+        } else {
+            None
+        }
+    }
+
+    pub fn can_go_next(&self) -> bool {
+        self.next_index().is_some()
+    }
+
+    pub fn can_go_prev(&self) -> bool {
+        self.previous_index().is_some()
+    }
+
+    pub fn go_next(&mut self) -> Option<&PlaylistItem> {
+        self.index = self.next_index()?;
+        self.current()
+    }
+
+    pub fn go_prev(&mut self) -> Option<&PlaylistItem> {
+        self.index = self.previous_index()?;
+        self.current()
+    }
+
+    pub fn jump(&mut self, index: usize) -> Option<&PlaylistItem> {
+        if index >= self.len() {
+            return None;
+        }
+        self.index = index;
+        self.current()
+    }
+
+    /// Remove an item. If it was the current one, the following item in play order becomes current, or the
+    /// previous one when it was the last.
+    pub fn remove(&mut self, index: usize) -> Option<PlaylistItem> {
+        if index >= self.len() {
+            return None;
+        }
+        let position = self.position();
+        let item = self.items.remove(index);
+        if let Some(ref mut order) = self.shuffle_order {
+            order.retain(|&i| i != index);
+            for i in order.iter_mut() {
+                if *i > index {
+                    *i -= 1;
+                }
+            }
+        }
+
+        if index < self.index {
+            self.index -= 1;
+        } else if index == self.index {
+            self.index = if self.is_empty() {
+                0
+            } else {
+                self.index_at(position.min(self.len() - 1))
+            };
+        }
+        Some(item)
+    }
+
+    pub fn move_item(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.len() || to >= self.len() {
+            return false;
+        }
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+
+        let remap = |i: usize| {
+            if i == from {
+                to
+            } else if from < i && i <= to {
+                i - 1
+            } else if to <= i && i < from {
+                i + 1
+            } else {
+                i
+            }
+        };
+        self.index = remap(self.index);
+        if let Some(ref mut order) = self.shuffle_order {
+            for i in order.iter_mut() {
+                *i = remap(*i);
+            }
+        }
+        true
+    }
+
+    /// Insert an item at the given index, returning the identifier assigned to it. When shuffling, the new item
+    /// gets a random spot among the ones not played yet.
+    pub fn insert(&mut self, index: usize, mut item: PlaylistItem) -> u64 {
+        let index = index.min(self.len());
+        self.assign_id(&mut item);
+        let id = item.id;
+        let was_empty = self.is_empty();
+        self.items.insert(index, item);
+        if !was_empty && index <= self.index {
+            self.index += 1;
+        }
+        let current = self.index;
+        if let Some(ref mut order) = self.shuffle_order {
+            for i in order.iter_mut() {
+                if *i >= index {
+                    *i += 1;
+                }
+            }
+            let position = order.iter().position(|&i| i == current).map_or(0, |p| p + 1);
+            let destination = self.rng.random_range(position..=order.len());
+            order.insert(destination, index);
+        }
+        id
+    }
+
+    /// Schedule an item to be played right after the current one.
+    pub fn play_next(&mut self, index: usize) -> bool {
+        if index >= self.len() {
+            return false;
+        }
+        if index == self.index {
+            return true;
+        }
+        let current = self.index;
+        if let Some(ref mut order) = self.shuffle_order {
+            order.retain(|&i| i != index);
+            let position = order.iter().position(|&i| i == current).unwrap_or(0);
+            order.insert(position + 1, index);
+            return true;
+        }
+        let destination = if index < self.index { self.index } else { self.index + 1 };
+        self.move_item(index, destination)
+    }
+
+    /// Item `advance()` would switch to, without making it current.
+    pub fn upcoming(&self) -> Option<&PlaylistItem> {
+        if self.loop_mode == LoopMode::Track {
+            return self.current();
+        }
+        self.items.get(self.next_index()?)
+    }
+
+    /// Item to play once the current one reached its end, repeating it if needed.
+    pub fn advance(&mut self) -> Option<&PlaylistItem> {
+        if self.loop_mode == LoopMode::Track {
+            return self.current();
+        }
+        self.go_next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(seed: u64, length: usize) -> Playlist {
+        let mut playlist = Playlist::new(Some(seed));
+        playlist.set_items(
+            (0..length)
+                .map(|i| PlaylistItem::new(format!("file:///{i}.mkv")))
+                .collect(),
+        );
+        playlist
+    }
+
+    fn play_order(playlist: &mut Playlist) -> Vec<usize> {
+        let mut order = vec![playlist.index()];
+        while playlist.go_next().is_some() {
+            order.push(playlist.index());
+        }
+        order
+    }
+
+    #[test]
+    fn sequential() {
+        let mut playlist = playlist(0, 3);
+        assert!(!playlist.can_go_prev());
+        assert_eq!(play_order(&mut playlist), vec![0, 1, 2]);
+        assert!(!playlist.can_go_next());
+        assert_eq!(playlist.go_prev().map(|item| item.uri.as_str()), Some("file:///1.mkv"));
+    }
+
+    #[test]
+    fn loop_modes() {
+        let mut playlist = playlist(0, 2);
+        playlist.set_loop_mode(LoopMode::Track);
+        assert_eq!(playlist.advance().map(|item| item.uri.as_str()), Some("file:///0.mkv"));
+        assert_eq!(playlist.index(), 0);
+        assert!(playlist.go_next().is_some());
+        assert!(playlist.advance().is_some());
+        assert_eq!(playlist.index(), 1);
+        assert!(playlist.go_next().is_none());
+
+        playlist.set_loop_mode(LoopMode::Playlist);
+        assert!(playlist.can_go_next());
+        assert!(playlist.advance().is_some());
+        assert_eq!(playlist.index(), 0);
+        assert!(playlist.go_prev().is_some());
+        assert_eq!(playlist.index(), 1);
+    }
+
+    #[test]
+    fn loop_mode_names() {
+        for mode in [LoopMode::None, LoopMode::Track, LoopMode::Playlist] {
+            assert_eq!(LoopMode::from_name(mode.as_str()), Some(mode));
+        }
+        assert_eq!(LoopMode::from_name("all"), None);
+    }
+
+    #[test]
+    fn loop_track_at_the_end() {
+        let mut playlist = playlist(0, 2);
+        playlist.jump(1);
+        playlist.set_loop_mode(LoopMode::Track);
+        assert!(!playlist.can_go_next());
+        assert!(playlist.can_go_prev());
+        for _ in 0..2 {
+            assert_eq!(playlist.advance().map(|item| item.uri.as_str()), Some("file:///1.mkv"));
+        }
+        assert_eq!(playlist.index(), 1);
+    }
+
+    #[test]
+    fn loop_empty_and_single_item_playlists() {
+        let mut empty = Playlist::new(None);
+        for mode in [LoopMode::Track, LoopMode::Playlist] {
+            empty.set_loop_mode(mode);
+            assert!(!empty.can_go_next());
+            assert!(!empty.can_go_prev());
+            assert!(empty.advance().is_none());
+        }
+
+        let mut playlist = playlist(0, 1);
+        playlist.set_loop_mode(LoopMode::Playlist);
+        assert!(playlist.can_go_next());
+        assert!(playlist.can_go_prev());
+        assert_eq!(playlist.advance().map(|item| item.uri.as_str()), Some("file:///0.mkv"));
+        assert_eq!(playlist.go_prev().map(|item| item.uri.as_str()), Some("file:///0.mkv"));
+        playlist.set_loop_mode(LoopMode::None);
+        assert!(playlist.advance().is_none());
+    }
+
+    #[test]
+    fn shuffle_is_a_permutation() {
+        let mut playlist = playlist(42, 20);
+        playlist.set_shuffle(true);
+        let order = play_order(&mut playlist);
+        assert_eq!(order[0], 0);
+
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+        assert_ne!(order, sorted);
+    }
+
+    #[test]
+    fn shuffle_is_deterministic_with_seed() {
+        let mut first = playlist(7, 10);
+        let mut second = playlist(7, 10);
+        first.set_shuffle(true);
+        second.set_shuffle(true);
+        assert_eq!(play_order(&mut first), play_order(&mut second));
+
+        let mut other = playlist(8, 10);
+        other.set_shuffle(true);
+        let mut first = playlist(7, 10);
+        first.set_shuffle(true);
+        assert_ne!(play_order(&mut first), play_order(&mut other));
+    }
+
+    #[test]
+    fn seed_reshuffles() {
+        let mut seeded = playlist(7, 10);
+        seeded.set_shuffle(true);
+        let mut reseeded = playlist(1, 10);
+        reseeded.set_shuffle(true);
+        reseeded.set_seed(7);
+        assert_eq!(play_order(&mut reseeded), play_order(&mut seeded));
+    }
+
+    #[test]
+    fn shuffle_previous_walks_back_history() {
+        let mut playlist = playlist(3, 8);
+        playlist.set_shuffle(true);
+        let mut history = vec![playlist.index()];
+        for _ in 0..4 {
+            playlist.go_next();
+            history.push(playlist.index());
+        }
+        while playlist.go_prev().is_some() {
+            history.pop();
+            assert_eq!(Some(&playlist.index()), history.last());
+        }
+        assert_eq!(history, vec![0]);
+    }
+
+    #[test]
+    fn shuffle_off_restores_sequential_order() {
+        let mut playlist = playlist(5, 6);
+        playlist.set_shuffle(true);
+        playlist.go_next();
+        let current = playlist.index();
+        let original: Vec<_> = playlist.items().iter().map(|item| item.uri.clone()).collect();
+
+        playlist.set_shuffle(false);
+        assert_eq!(playlist.index(), current);
+        assert_eq!(play_order(&mut playlist), (current..6).collect::<Vec<_>>());
+        let items: Vec<_> = playlist.items().iter().map(|item| item.uri.clone()).collect();
+        assert_eq!(items, original);
+    }
+
+    fn uris(playlist: &Playlist) -> Vec<&str> {
+        playlist.items().iter().map(|item| item.uri.as_str()).collect()
+    }
+
+    #[test]
+    fn jump() {
+        let mut playlist = playlist(0, 3);
+        assert!(playlist.jump(3).is_none());
+        assert_eq!(playlist.jump(2).map(|item| item.uri.as_str()), Some("file:///2.mkv"));
+        assert!(playlist.can_go_prev());
+        assert!(!playlist.can_go_next());
+    }
+
+    #[test]
+    fn remove_keeps_current_item() {
+        let mut playlist = playlist(0, 5);
+        playlist.jump(2);
+
+        assert!(playlist.remove(5).is_none());
+        assert_eq!(
+            playlist.remove(4).map(|item| item.uri),
+            Some("file:///4.mkv".to_string())
+        );
+        assert_eq!(playlist.index(), 2);
+        playlist.remove(0);
+        assert_eq!(playlist.index(), 1);
+        assert_eq!(playlist.current().map(|item| item.uri.as_str()), Some("file:///2.mkv"));
+
+        // Removing the current item makes the following one current.
+        playlist.remove(1);
+        assert_eq!(uris(&playlist), vec!["file:///1.mkv", "file:///3.mkv"]);
+        assert_eq!(playlist.current().map(|item| item.uri.as_str()), Some("file:///3.mkv"));
+        playlist.remove(1);
+        assert_eq!(playlist.current().map(|item| item.uri.as_str()), Some("file:///1.mkv"));
+        playlist.remove(0);
+        assert!(playlist.is_empty());
+        assert!(playlist.current().is_none());
+        assert!(!playlist.can_go_next());
+        assert!(!playlist.can_go_prev());
+    }
+
+    #[test]
+    fn remove_last_current_item() {
+        let mut sequential = playlist(0, 3);
+        sequential.jump(2);
+        sequential.remove(2);
+        assert_eq!(sequential.index(), 1);
+        assert_eq!(
+            sequential.current().map(|item| item.uri.as_str()),
+            Some("file:///1.mkv")
+        );
+        assert!(!sequential.can_go_next());
+
+        // The previous item in play order, when shuffled.
+        let mut shuffled = playlist(4, 4);
+        shuffled.set_shuffle(true);
+        let order = play_order(&mut shuffled);
+        let previous = shuffled.items()[order[2]].clone();
+        shuffled.remove(order[3]);
+        assert_eq!(shuffled.current(), Some(&previous));
+        assert!(!shuffled.can_go_next());
+    }
+
+    #[test]
+    fn remove_while_shuffled() {
+        let mut playlist = playlist(9, 6);
+        playlist.set_shuffle(true);
+        playlist.go_next();
+        playlist.go_next();
+        let next = playlist.current().cloned();
+        playlist.go_prev();
+
+        let mut items = playlist.items().to_vec();
+        let index = playlist.index();
+        playlist.remove(index);
+        items.remove(index);
+        assert_eq!(playlist.items(), &items[..]);
+        assert_eq!(playlist.current(), next.as_ref());
+
+        let mut order = play_order(&mut playlist);
+        assert!(order.iter().all(|&i| i < 5));
+        let length = order.len();
+        order.sort();
+        order.dedup();
+        assert_eq!(order.len(), length);
+    }
+
+    #[test]
+    fn move_item() {
+        let mut playlist = playlist(0, 4);
+        playlist.jump(1);
+
+        assert!(!playlist.move_item(0, 4));
+        assert!(playlist.move_item(0, 3));
+        assert_eq!(
+            uris(&playlist),
+            vec!["file:///1.mkv", "file:///2.mkv", "file:///3.mkv", "file:///0.mkv"]
+        );
+        assert_eq!(playlist.index(), 0);
+
+        assert!(playlist.move_item(2, 0));
+        assert_eq!(
+            uris(&playlist),
+            vec!["file:///3.mkv", "file:///1.mkv", "file:///2.mkv", "file:///0.mkv"]
+        );
+        assert_eq!(playlist.index(), 1);
+
+        assert!(playlist.move_item(1, 2));
+        assert_eq!(playlist.index(), 2);
+        assert_eq!(playlist.current().map(|item| item.uri.as_str()), Some("file:///1.mkv"));
+    }
+
+    #[test]
+    fn play_next() {
+        let mut playlist = playlist(0, 5);
+        playlist.jump(2);
+
+        assert!(playlist.play_next(0));
+        assert_eq!(playlist.current().map(|item| item.uri.as_str()), Some("file:///2.mkv"));
+        assert_eq!(playlist.go_next().map(|item| item.uri.as_str()), Some("file:///0.mkv"));
+
+        assert!(playlist.play_next(4));
+        assert_eq!(playlist.go_next().map(|item| item.uri.as_str()), Some("file:///4.mkv"));
+        assert_eq!(playlist.go_next().map(|item| item.uri.as_str()), Some("file:///3.mkv"));
+    }
+
+    #[test]
+    fn play_next_while_shuffled() {
+        let mut playlist = playlist(4, 10);
+        playlist.set_shuffle(true);
+        let items = playlist.items().to_vec();
+        let candidate = (0..10).find(|&i| i != playlist.index()).unwrap();
+
+        assert!(playlist.play_next(candidate));
+        assert_eq!(playlist.items(), &items[..]);
+        assert_eq!(
+            playlist.go_next().map(|item| item.uri.clone()),
+            Some(items[candidate].uri.clone())
+        );
+        let mut order = play_order(&mut playlist);
+        order.sort();
+        order.dedup();
+        assert_eq!(order.len(), 9);
+    }
+
+    #[test]
+    fn shuffle_new_items() {
+        let mut playlist = playlist(11, 0);
+        playlist.set_shuffle(true);
+        playlist.set_items((0..5).map(|i| PlaylistItem::new(format!("file:///{i}.mkv"))).collect());
+        playlist.set_loop_mode(LoopMode::Playlist);
+        let first = playlist.index();
+        for _ in 0..4 {
+            playlist.go_next();
+        }
+        assert!(playlist.go_next().is_some());
+        assert_eq!(playlist.index(), first);
+    }
+
+    #[test]
+    fn stable_ids() {
+        let mut playlist = playlist(0, 4);
+        let ids: Vec<u64> = playlist.items().iter().map(|item| item.id).collect();
+        assert!(ids.iter().all(|&id| id != 0));
+        assert_eq!(playlist.position_of(ids[2]), Some(2));
+
+        playlist.move_item(2, 0);
+        playlist.remove(1);
+        assert_eq!(playlist.position_of(ids[2]), Some(0));
+        assert_eq!(playlist.position_of(ids[0]), None);
+
+        let id = playlist.insert(1, PlaylistItem::new("file:///new.mkv"));
+        assert!(!ids.contains(&id));
+        assert_eq!(playlist.position_of(id), Some(1));
+    }
+
+    #[test]
+    fn insert() {
+        let mut playlist = playlist(0, 3);
+        playlist.jump(1);
+        playlist.insert(0, PlaylistItem::new("file:///new.mkv"));
+        assert_eq!(playlist.index(), 2);
+        assert_eq!(playlist.current().unwrap().uri, "file:///1.mkv");
+        playlist.insert(10, PlaylistItem::new("file:///last.mkv"));
+        assert_eq!(
+            uris(&playlist),
+            [
+                "file:///new.mkv",
+                "file:///0.mkv",
+                "file:///1.mkv",
+                "file:///2.mkv",
+                "file:///last.mkv"
+            ]
+        );
+
+        let mut empty = Playlist::default();
+        empty.insert(0, PlaylistItem::new("file:///new.mkv"));
+        assert_eq!(empty.current().unwrap().uri, "file:///new.mkv");
+    }
+
+    #[test]
+    fn insert_while_shuffled() {
+        let mut playlist = playlist(5, 6);
+        playlist.set_shuffle(true);
+        playlist.go_next();
+        let current = playlist.current().unwrap().uri.clone();
+        playlist.insert(0, PlaylistItem::new("file:///new.mkv"));
+        assert_eq!(playlist.current().unwrap().uri, current);
+
+        let remaining: Vec<string::String> =
+            std::iter::from_fn(|| playlist.go_next().map(|item| item.uri.clone())).collect();
+        assert_eq!(remaining.len(), 5);
+        assert!(remaining.iter().any(|uri| uri == "file:///new.mkv"));
+    }
+
+    #[test]
+    fn upcoming() {
+        let mut playlist = playlist(8, 3);
+        playlist.set_shuffle(true);
+        for _ in 0..2 {
+            let upcoming = playlist.upcoming().cloned();
+            assert_eq!(playlist.advance().cloned(), upcoming);
+        }
+        assert_eq!(playlist.upcoming(), None);
+
+        playlist.set_loop_mode(LoopMode::Track);
+        assert_eq!(playlist.upcoming(), playlist.current());
+        playlist.set_loop_mode(LoopMode::Playlist);
+        let upcoming = playlist.upcoming().cloned();
+        assert!(upcoming.is_some());
+        assert_eq!(playlist.advance().cloned(), upcoming);
+    }
+}
@@ -0,0 +1,469 @@
+use crate::media_directory;
+use crate::playlist::PlaylistItem;
+use gstreamer::glib;
+use std::collections::BTreeMap;
+use std::path;
+use std::string;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaylistFormat {
+    M3u,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    pub fn from_path(path: &path::Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(Self::M3u),
+            "pls" => Some(Self::Pls),
+            "xspf" => Some(Self::Xspf),
+            _ => None,
+        }
+    }
+}
+
+/// Expand a playlist file URI to its entries and a directory URI to the media files it contains. Any other
+/// URI is returned as a single item.
+pub fn expand_uri(uri: &str) -> Vec<PlaylistItem> {
+    if let Ok((path, _)) = glib::filename_from_uri(uri) {
+        if path.is_dir() {
+            return media_directory::expand(&path, media_directory::MAX_DEPTH)
+                .into_iter()
+                .map(PlaylistItem::new)
+                .collect();
+        }
+        if PlaylistFormat::from_path(&path).is_some() {
+            return match load(&path) {
+                Ok(items) => items,
+                Err(e) => {
+                    eprintln!("Unable to load playlist {}: {e}", path.display());
+                    vec![]
+                }
+            };
+        }
+    }
+    vec![PlaylistItem::new(uri)]
+}
+
+pub fn load(path: &path::Path) -> anyhow::Result<Vec<PlaylistItem>> {
+    let format = PlaylistFormat::from_path(path).ok_or(anyhow::anyhow!("Unsupported playlist format"))?;
+    let path = std::path::absolute(path)?;
+    let base_dir = path
+        .parent()
+        .ok_or(anyhow::anyhow!("Unable to determine playlist directory"))?;
+
+    // M3U files are not required to be UTF-8 encoded, so be lenient here.
+    let data = std::fs::read(&path)?;
+    let contents = string::String::from_utf8_lossy(&data);
+    parse(format, &contents, base_dir)
+}
+
+pub fn save(path: &path::Path, items: &[PlaylistItem]) -> anyhow::Result<()> {
+    let format = PlaylistFormat::from_path(path).unwrap_or(PlaylistFormat::M3u);
+    let path = std::path::absolute(path)?;
+    let base_dir = path
+        .parent()
+        .ok_or(anyhow::anyhow!("Unable to determine playlist directory"))?;
+
+    let contents = match format {
+        PlaylistFormat::M3u => to_m3u(items, base_dir),
+        PlaylistFormat::Xspf => to_xspf(items, base_dir),
+        PlaylistFormat::Pls => return Err(anyhow::anyhow!("Saving to PLS is not supported")),
+    };
+    std::fs::write(&path, contents)?;
+    Ok(())
+}
+
+pub fn parse(format: PlaylistFormat, contents: &str, base_dir: &path::Path) -> anyhow::Result<Vec<PlaylistItem>> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    match format {
+        PlaylistFormat::M3u => Ok(parse_m3u(contents, base_dir)),
+        PlaylistFormat::Pls => Ok(parse_pls(contents, base_dir)),
+        PlaylistFormat::Xspf => parse_xspf(contents, base_dir),
+    }
+}
+
+fn parse_m3u(contents: &str, base_dir: &path::Path) -> Vec<PlaylistItem> {
+    let mut items = vec![];
+    let mut pending_info: Option<(Option<gst::ClockTime>, Option<string::String>)> = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            pending_info = Some(parse_extinf(info));
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some(uri) = resolve_location(line, base_dir) {
+            let mut item = PlaylistItem::new(uri);
+            if let Some((duration, title)) = pending_info.take() {
+                item.duration = duration;
+                item.title = title;
+            }
+            items.push(item);
+        }
+        pending_info = None;
+    }
+
+    items
+}
+
+// Format is `<duration>[ key="value"...],<title>`, the attributes possibly containing commas.
+fn parse_extinf(info: &str) -> (Option<gst::ClockTime>, Option<string::String>) {
+    let mut in_quotes = false;
+    let separator = info.char_indices().find_map(|(i, c)| {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        (!in_quotes && c == ',').then_some(i)
+    });
+
+    let (head, title) = match separator {
+        Some(i) => (&info[..i], Some(info[i + 1..].trim())),
+        None => (info, None),
+    };
+    let duration = head.split_whitespace().next().and_then(parse_seconds);
+    let title = title.filter(|t| !t.is_empty()).map(string::String::from);
+    (duration, title)
+}
+
+#[derive(Default)]
+struct PlsEntry {
+    location: Option<string::String>,
+    title: Option<string::String>,
+    duration: Option<gst::ClockTime>,
+}
+
+fn parse_pls(contents: &str, base_dir: &path::Path) -> Vec<PlaylistItem> {
+    let mut entries: BTreeMap<u32, PlsEntry> = BTreeMap::new();
+
+    for line in contents.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        let Some(position) = key.find(|c: char| c.is_ascii_digit()) else {
+            continue;
+        };
+        let Ok(number) = key[position..].parse::<u32>() else {
+            continue;
+        };
+
+        let entry = entries.entry(number).or_default();
+        match &key[..position] {
+            "file" => entry.location = Some(value.to_string()),
+            "title" if !value.is_empty() => entry.title = Some(value.to_string()),
+            "length" => entry.duration = parse_seconds(value),
+            _ => {}
+        }
+    }
+
+    entries
+        .into_values()
+        .filter_map(|entry| {
+            let uri = resolve_location(&entry.location?, base_dir)?;
+            Some(PlaylistItem {
+                title: entry.title,
+                duration: entry.duration,
+                ..PlaylistItem::new(uri)
+            })
+        })
+        .collect()
+}
+
+fn parse_xspf(contents: &str, base_dir: &path::Path) -> anyhow::Result<Vec<PlaylistItem>> {
+    let document = roxmltree::Document::parse(contents)?;
+    let mut items = vec![];
+
+    for track in document.descendants().filter(|n| n.has_tag_name("track")) {
+        let child_text = |name: &str| {
+            track
+                .children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text())
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+        };
+
+        let Some(uri) = child_text("location").and_then(|l| resolve_uri_reference(l, base_dir)) else {
+            continue;
+        };
+        let title = child_text("title").map(string::String::from);
+        let duration = child_text("duration")
+            .and_then(|d| d.parse::<u64>().ok())
+            .map(gst::ClockTime::from_mseconds);
+        items.push(PlaylistItem {
+            title,
+            duration,
+            ..PlaylistItem::new(uri)
+        });
+    }
+
+    Ok(items)
+}
+
+fn to_m3u(items: &[PlaylistItem], base_dir: &path::Path) -> string::String {
+    let mut contents = string::String::from("#EXTM3U\n");
+    for item in items {
+        if item.title.is_some() || item.duration.is_some() {
+            let duration = item.duration.map(|d| d.seconds() as i64).unwrap_or(-1);
+            // Line breaks, common in stream tags, would end the directive.
+            let title = item.title.as_deref().unwrap_or_default().replace(['\r', '\n'], " ");
+            contents.push_str(&format!("#EXTINF:{duration},{title}\n"));
+        }
+
+        // Keep local files next to the playlist relative, so it can be moved along with them.
+        let location = match glib::filename_from_uri(&item.uri) {
+            Ok((path, _)) => path
+                .strip_prefix(base_dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string(),
+            Err(_) => item.uri.clone(),
+        };
+        contents.push_str(&location);
+        contents.push('\n');
+    }
+    contents
+}
+
+fn to_xspf(items: &[PlaylistItem], base_dir: &path::Path) -> string::String {
+    let base_uri = glib::filename_to_uri(base_dir, None)
+        .map(|uri| format!("{}/", uri.trim_end_matches('/')))
+        .ok();
+
+    let mut contents = string::String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n",
+    );
+    for item in items {
+        let location = base_uri
+            .as_deref()
+            .and_then(|base_uri| item.uri.strip_prefix(base_uri))
+            .unwrap_or(&item.uri);
+        contents.push_str("    <track>\n");
+        contents.push_str(&format!("      <location>{}</location>\n", escape_xml(location)));
+        if let Some(ref title) = item.title {
+            contents.push_str(&format!("      <title>{}</title>\n", escape_xml(title)));
+        }
+        if let Some(duration) = item.duration {
+            contents.push_str(&format!("      <duration>{}</duration>\n", duration.mseconds()));
+        }
+        contents.push_str("    </track>\n");
+    }
+    contents.push_str("  </trackList>\n</playlist>\n");
+    contents
+}
+
+fn escape_xml(text: &str) -> string::String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn parse_seconds(value: &str) -> Option<gst::ClockTime> {
+    // Negative values are used to flag unknown durations.
+    let seconds = value.trim().parse::<f64>().ok()?;
+    if seconds < 0.0 {
+        return None;
+    }
+    Some(gst::ClockTime::from_nseconds((seconds * 1_000_000_000.0) as u64))
+}
+
+// M3U and PLS entries are either URIs or local paths, relative to the playlist directory.
+fn resolve_location(location: &str, base_dir: &path::Path) -> Option<string::String> {
+    if glib::uri_parse_scheme(location).is_some() {
+        return Some(location.to_string());
+    }
+    let path = base_dir.join(location).components().collect::<path::PathBuf>();
+    glib::filename_to_uri(path, None).ok().map(string::String::from)
+}
+
+// XSPF locations are URI references, possibly relative to the playlist location.
+fn resolve_uri_reference(reference: &str, base_dir: &path::Path) -> Option<string::String> {
+    if glib::uri_parse_scheme(reference).is_some() {
+        return Some(reference.to_string());
+    }
+    let base_uri = glib::filename_to_uri(base_dir, None).ok()?;
+    let base_uri = format!("{}/", base_uri.trim_end_matches('/'));
+    glib::Uri::resolve_relative(Some(&base_uri), reference, glib::UriFlags::NONE)
+        .ok()
+        .map(string::String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures_dir() -> path::PathBuf {
+        path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/playlists")
+    }
+
+    fn fixture_uri(relative_path: &str) -> string::String {
+        glib::filename_to_uri(fixtures_dir().join(relative_path), None)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn format_from_extension() {
+        let format = |name: &str| PlaylistFormat::from_path(path::Path::new(name));
+        assert_eq!(format("reviews.m3u"), Some(PlaylistFormat::M3u));
+        assert_eq!(format("reviews.M3U8"), Some(PlaylistFormat::M3u));
+        assert_eq!(format("radio.pls"), Some(PlaylistFormat::Pls));
+        assert_eq!(format("reviews.xspf"), Some(PlaylistFormat::Xspf));
+        assert_eq!(format("clip.mkv"), None);
+        assert_eq!(format("m3u"), None);
+    }
+
+    #[test]
+    fn extended_m3u() {
+        let items = load(&fixtures_dir().join("reviews.m3u8")).unwrap();
+        assert_eq!(items.len(), 4);
+
+        assert_eq!(items[0].uri, fixture_uri("clips/take 1.mkv"));
+        assert_eq!(items[0].title.as_deref(), Some("Take 1, first pass"));
+        assert_eq!(items[0].duration, Some(gst::ClockTime::from_seconds(93)));
+
+        assert_eq!(items[1].uri, "file:///srv/media/review/take2.mkv");
+        assert_eq!(items[1].title.as_deref(), Some("Take 2"));
+        assert_eq!(items[1].duration, Some(gst::ClockTime::from_mseconds(12_500)));
+
+        assert_eq!(items[2].uri, "https://example.com/streams/live.m3u8");
+        assert_eq!(items[2].title.as_deref(), Some("Live, \"studio\" feed"));
+        assert_eq!(items[2].duration, None);
+
+        assert_eq!(items[3], PlaylistItem::new(fixture_uri("clips/b-roll.mp4")));
+    }
+
+    #[test]
+    fn plain_m3u() {
+        let items = load(&fixtures_dir().join("plain.m3u")).unwrap();
+        assert_eq!(
+            items,
+            vec![
+                PlaylistItem::new(fixture_uri("clips/take 1.mkv")),
+                PlaylistItem::new(fixture_uri("clips/b-roll.mp4")),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls() {
+        let items = load(&fixtures_dir().join("reviews.pls")).unwrap();
+        assert_eq!(items.len(), 3);
+
+        assert_eq!(items[0].uri, fixture_uri("clips/take 1.mkv"));
+        assert_eq!(items[0].title.as_deref(), Some("Take 1"));
+        assert_eq!(items[0].duration, Some(gst::ClockTime::from_seconds(93)));
+
+        assert_eq!(items[1].uri, "http://radio.example.com:8000/stream");
+        assert_eq!(items[1].title.as_deref(), Some("Radio"));
+        assert_eq!(items[1].duration, None);
+
+        assert_eq!(items[2].uri, fixture_uri("clips/b-roll.mp4"));
+        assert_eq!(items[2].title, None);
+    }
+
+    #[test]
+    fn xspf() {
+        let items = load(&fixtures_dir().join("reviews.xspf")).unwrap();
+        assert_eq!(items.len(), 3);
+
+        assert_eq!(items[0].uri, fixture_uri("clips/take 1.mkv"));
+        assert_eq!(items[0].title.as_deref(), Some("Take 1 & notes"));
+        assert_eq!(items[0].duration, Some(gst::ClockTime::from_mseconds(93_000)));
+
+        assert_eq!(items[1].uri, "file:///srv/media/review/take2.mkv");
+        assert_eq!(items[1].title.as_deref(), Some("Take 2"));
+        assert_eq!(items[1].duration, None);
+
+        assert_eq!(items[2].uri, fixture_uri("clips/b-roll.mp4"));
+        assert_eq!(items[2].title, None);
+    }
+
+    fn saved_items() -> Vec<PlaylistItem> {
+        vec![
+            PlaylistItem {
+                title: Some("Take 1 <final> & \"approved\"".to_string()),
+                duration: Some(gst::ClockTime::from_seconds(93)),
+                ..PlaylistItem::new(fixture_uri("clips/take 1.mkv"))
+            },
+            PlaylistItem::new("file:///srv/media/review/take2.mkv"),
+            PlaylistItem {
+                title: Some("Live".to_string()),
+                ..PlaylistItem::new("https://example.com/streams/live.m3u8")
+            },
+        ]
+    }
+
+    #[test]
+    fn m3u_round_trip() {
+        let contents = to_m3u(&saved_items(), &fixtures_dir());
+        assert!(contents.starts_with("#EXTM3U\n#EXTINF:93,Take 1"));
+        assert!(contents.contains("\nclips/take 1.mkv\n"));
+        assert!(contents.contains("\n/srv/media/review/take2.mkv\n"));
+        assert!(contents.contains("#EXTINF:-1,Live\n"));
+        assert_eq!(
+            parse(PlaylistFormat::M3u, &contents, &fixtures_dir()).unwrap(),
+            saved_items()
+        );
+    }
+
+    #[test]
+    fn m3u_multiline_title() {
+        let items = vec![PlaylistItem {
+            title: Some("Artist\r\nSong\nLive".to_string()),
+            ..PlaylistItem::new("https://example.com/radio")
+        }];
+        let contents = to_m3u(&items, &fixtures_dir());
+        assert_eq!(
+            contents,
+            "#EXTM3U\n#EXTINF:-1,Artist  Song Live\nhttps://example.com/radio\n"
+        );
+        let parsed = parse(PlaylistFormat::M3u, &contents, &fixtures_dir()).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].title.as_deref(), Some("Artist  Song Live"));
+    }
+
+    #[test]
+    fn xspf_round_trip() {
+        let contents = to_xspf(&saved_items(), &fixtures_dir());
+        assert!(contents.contains("<location>clips/take%201.mkv</location>"));
+        assert!(contents.contains("<title>Take 1 &lt;final&gt; &amp; &quot;approved&quot;</title>"));
+        assert!(contents.contains("<duration>93000</duration>"));
+        assert_eq!(
+            parse(PlaylistFormat::Xspf, &contents, &fixtures_dir()).unwrap(),
+            saved_items()
+        );
+    }
+
+    #[test]
+    fn save_to_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("session");
+        save(&path, &saved_items()).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("#EXTM3U"));
+        assert!(save(&directory.path().join("session.pls"), &saved_items()).is_err());
+    }
+
+    #[test]
+    fn invalid_xspf() {
+        assert!(parse(PlaylistFormat::Xspf, "<playlist><trackList>", &fixtures_dir()).is_err());
+    }
+
+    #[test]
+    fn expand_non_playlist_uri() {
+        let uri = "https://example.com/clip.webm";
+        assert_eq!(expand_uri(uri), vec![PlaylistItem::new(uri)]);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::media_cache;
+use crate::playlist::PlaylistItem;
+use std::fs::File;
+use std::io::Read;
+use std::path;
+
+/// State of the player when it was last closed, restored on next startup.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Session {
+    pub playlist: Vec<PlaylistItem>,
+    pub index: usize,
+    pub volume: f64,
+    pub rate: f64,
+}
+
+impl Session {
+    pub fn read<T: AsRef<path::Path>>(path: T) -> anyhow::Result<Self> {
+        let mut file = File::open(path.as_ref())?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn write<T: AsRef<path::Path>>(&self, path: T) -> anyhow::Result<()> {
+        media_cache::write_json(path.as_ref(), self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        let mut item = PlaylistItem::new("file:///a.mkv");
+        item.title = Some("A".into());
+        let session = Session {
+            playlist: vec![item, PlaylistItem::new("file:///b.ogg")],
+            index: 1,
+            volume: 0.5,
+            rate: 1.25,
+        };
+        session.write(&path).unwrap();
+        assert_eq!(Session::read(&path).unwrap(), session);
+
+        // Replaced as a whole.
+        let empty = Session {
+            playlist: vec![],
+            index: 0,
+            volume: 1.0,
+            rate: 1.0,
+        };
+        empty.write(&path).unwrap();
+        assert_eq!(Session::read(&path).unwrap(), empty);
+        assert_eq!(dir.path().read_dir().unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,161 @@
+use directories::ProjectDirs;
+use std::fs::{create_dir_all, File};
+use std::io::{Read, Write};
+use std::path;
+
+/// Whether playback resumes where it stopped the last time a media was played.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResumeMode {
+    Always,
+    #[default]
+    Ask,
+    Never,
+}
+
+impl ResumeMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Ask => "ask",
+            Self::Never => "never",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(Self::Always),
+            "ask" => Some(Self::Ask),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Appearance of the text subtitles. Colors are written as `#rrggbbaa`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SubtitleStyle {
+    /// Pango font description, with the family and the size.
+    pub font: String,
+    pub text_color: String,
+    pub outline: bool,
+    pub outline_color: String,
+    /// Opacity of the box drawn behind the text, from 0 (no box) to 1.
+    pub background_opacity: f64,
+    /// Space between the subtitles and the bottom of the video, in pixels.
+    pub margin: u32,
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        Self {
+            font: "Sans 18".into(),
+            text_color: "#ffffffff".into(),
+            outline: true,
+            outline_color: "#000000ff".into(),
+            background_opacity: 0.0,
+            margin: 25,
+        }
+    }
+}
+
+/// A `#rrggbb` or `#rrggbbaa` color as the 0xAARRGGBB value used by the GStreamer text overlay.
+pub fn color_to_argb(color: &str) -> Option<u32> {
+    let hex = color.strip_prefix('#')?;
+    let rgba = match hex.len() {
+        6 => u32::from_str_radix(hex, 16).ok()? << 8 | 0xff,
+        8 => u32::from_str_radix(hex, 16).ok()?,
+        _ => return None,
+    };
+    Some(rgba.rotate_right(8))
+}
+
+/// User preferences, stored in the configuration directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Reload the playlist of the previous session when started without files.
+    pub restore_session: bool,
+    /// Queue the following episodes of a series when a single one of them is opened.
+    pub queue_next_episodes: bool,
+    pub resume: ResumeMode,
+    pub subtitle_style: SubtitleStyle,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            restore_session: true,
+            queue_next_episodes: true,
+            resume: ResumeMode::default(),
+            subtitle_style: SubtitleStyle::default(),
+        }
+    }
+}
+
+fn settings_path() -> Option<path::PathBuf> {
+    ProjectDirs::from("net", "baseart", "Glide").map(|d| d.config_dir().join("settings.json"))
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let Some(path) = settings_path() else {
+            return Self::default();
+        };
+        match Self::read(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                if path.exists() {
+                    eprintln!("Unable to read settings from {}: {e}", path.display());
+                }
+                Self::default()
+            }
+        }
+    }
+
+    fn read(path: &path::Path) -> anyhow::Result<Self> {
+        let mut file = File::open(path)?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = settings_path().ok_or(anyhow::anyhow!("Unable to determine configuration directory"))?;
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let mut file = File::create(&path)?;
+        let json = serde_json::to_string_pretty(self)?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!(color_to_argb("#ffffffff"), Some(0xffffffff));
+        assert_eq!(color_to_argb("#12345678"), Some(0x78123456));
+        assert_eq!(color_to_argb("#123456"), Some(0xff123456));
+        assert_eq!(color_to_argb("123456"), None);
+        assert_eq!(color_to_argb("#12345"), None);
+        assert_eq!(color_to_argb("#zzzzzz"), None);
+    }
+
+    #[test]
+    fn settings_of_older_releases() {
+        let settings: Settings = serde_json::from_str(r#"{"restore_session": false, "resume": "never"}"#).unwrap();
+        assert!(!settings.restore_session);
+        assert_eq!(settings.resume, ResumeMode::Never);
+        assert_eq!(settings.subtitle_style, SubtitleStyle::default());
+
+        let settings: Settings = serde_json::from_str(r#"{"subtitle_style": {"margin": 60}}"#).unwrap();
+        assert_eq!(settings.subtitle_style.margin, 60);
+        assert_eq!(settings.subtitle_style.font, "Sans 18");
+    }
+}
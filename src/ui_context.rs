@@ -4,7 +4,7 @@ extern crate gstreamer as gst;
 extern crate gtk4 as gtk;
 extern crate open;
 
-use adw::prelude::MessageDialogExt;
+use adw::prelude::{ActionRowExt, MessageDialogExt};
 use gettextrs::*;
 #[allow(unused_imports)]
 use gio::prelude::*;
@@ -13,15 +13,18 @@ use glib::SendWeakRef;
 use gstreamer::glib;
 use gtk::gdk;
 use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
 use std::io::Write;
 #[allow(unused_imports)]
 use std::os::raw::c_void;
 use std::path;
+use std::rc::Rc;
 use std::string;
 use std::sync::Mutex;
 
 use crate::config;
 use crate::debug_infos::DebugInfos;
+use crate::settings::SubtitleStyle;
 use crate::PlaybackState;
 
 lazy_static! {
@@ -30,6 +33,7 @@ lazy_static! {
     pub static ref INITIAL_SIZE: Mutex<Option<(i32, i32)>> = Mutex::new(None);
     pub static ref AUTOHIDE_SOURCE: Mutex<Option<glib::SourceId>> = Mutex::new(None);
     pub static ref AUTO_HIDE_TIMEOUT_SECONDS: u32 = 2;
+    pub static ref RESUME_PROMPT_TIMEOUT_SECONDS: u32 = 15;
 }
 
 #[cfg(target_os = "macos")]
@@ -38,7 +42,7 @@ use crate::iokit_sleep_disabler;
 pub fn create_app() -> adw::Application {
     let gtk_app = adw::Application::builder()
         .application_id(config::app_id())
-        .flags(gio::ApplicationFlags::HANDLES_OPEN)
+        .flags(gio::ApplicationFlags::HANDLES_COMMAND_LINE)
         .build();
 
     let style_manager = adw::StyleManager::default();
@@ -47,6 +51,86 @@ pub fn create_app() -> adw::Application {
     gtk_app
 }
 
+pub struct PlaylistRow {
+    pub title: String,
+    pub duration: Option<gst::ClockTime>,
+    pub current: bool,
+}
+
+pub struct RecentRow {
+    pub uri: String,
+    pub title: String,
+    // Fraction of the media already played.
+    pub progress: f64,
+    // Unknown for streams and for the media played until the end.
+    pub remaining: Option<gst::ClockTime>,
+}
+
+// Controls of the subtitle style window.
+#[derive(Clone)]
+struct SubtitleStyleWidgets {
+    font_button: gtk::FontDialogButton,
+    color_button: gtk::ColorDialogButton,
+    outline_row: adw::SwitchRow,
+    outline_color_button: gtk::ColorDialogButton,
+    background_scale: gtk::Scale,
+    margin_row: adw::SpinRow,
+}
+
+fn color_string(rgba: &gdk::RGBA) -> string::String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}{:02x}",
+        channel(rgba.red()),
+        channel(rgba.green()),
+        channel(rgba.blue()),
+        channel(rgba.alpha())
+    )
+}
+
+impl SubtitleStyleWidgets {
+    fn new(builder: &gtk::Builder) -> Self {
+        Self {
+            font_button: builder.object("subtitle-font-button").unwrap(),
+            color_button: builder.object("subtitle-color-button").unwrap(),
+            outline_row: builder.object("subtitle-outline-row").unwrap(),
+            outline_color_button: builder.object("subtitle-outline-color-button").unwrap(),
+            background_scale: builder.object("subtitle-background-scale").unwrap(),
+            margin_row: builder.object("subtitle-margin-row").unwrap(),
+        }
+    }
+
+    fn style(&self) -> SubtitleStyle {
+        let defaults = SubtitleStyle::default();
+        SubtitleStyle {
+            font: self
+                .font_button
+                .font_desc()
+                .map(|font| font.to_str().to_string())
+                .unwrap_or(defaults.font),
+            text_color: color_string(&self.color_button.rgba()),
+            outline: self.outline_row.is_active(),
+            outline_color: color_string(&self.outline_color_button.rgba()),
+            background_opacity: self.background_scale.value(),
+            margin: self.margin_row.value() as u32,
+        }
+    }
+
+    fn set_style(&self, style: &SubtitleStyle) {
+        self.font_button
+            .set_font_desc(&gtk::pango::FontDescription::from_string(&style.font));
+        if let Ok(rgba) = gdk::RGBA::parse(style.text_color.as_str()) {
+            self.color_button.set_rgba(&rgba);
+        }
+        self.outline_row.set_active(style.outline);
+        if let Ok(rgba) = gdk::RGBA::parse(style.outline_color.as_str()) {
+            self.outline_color_button.set_rgba(&rgba);
+        }
+        self.background_scale.set_value(style.background_opacity);
+        self.margin_row.set_value(style.margin as f64);
+    }
+}
+
 pub struct UIContext {
     window: adw::ApplicationWindow,
     header_bar: gtk::HeaderBar,
@@ -54,17 +138,35 @@ pub struct UIContext {
     video_renderer: gtk::Picture,
     pause_button: gtk::Button,
     progress_bar: gtk::Scale,
+    ab_repeat_area: gtk::DrawingArea,
+    // Start and end of the A–B loop, in seconds.
+    ab_repeat_range: Rc<Cell<(Option<f64>, Option<f64>)>>,
     #[allow(deprecated)]
     volume_button: gtk::VolumeButton,
     toolbar_revealer: gtk::Revealer,
     track_synchronization_window: adw::ApplicationWindow,
+    subtitle_style_window: adw::ApplicationWindow,
+    subtitle_style_widgets: SubtitleStyleWidgets,
     shortcuts_window: gtk::ShortcutsWindow,
     audio_offset_entry: gtk::SpinButton,
     subtitle_offset_entry: gtk::SpinButton,
     subtitle_track_menu: gio::Menu,
     audio_track_menu: gio::Menu,
     video_track_menu: gio::Menu,
+    bookmark_list_menu: gio::Menu,
+    chapter_list_menu: gio::Menu,
+    window_title: adw::WindowTitle,
     audio_visualization_menu: gio::Menu,
+    playlist_store: gio::ListStore,
+    playlist_selection: gtk::SingleSelection,
+    toast_overlay: adw::ToastOverlay,
+    resume_revealer: gtk::Revealer,
+    resume_label: gtk::Label,
+    resume_timeout: Rc<RefCell<Option<glib::SourceId>>>,
+    content_stack: gtk::Stack,
+    recent_box: gtk::Box,
+    recent_list: gtk::ListBox,
+    recent_menu: gio::Menu,
     volume_signal_handler_id: Option<glib::SignalHandlerId>,
     position_signal_handler_id: Option<glib::SignalHandlerId>,
     audio_offset_entry_signal_handler_id: Option<glib::SignalHandlerId>,
@@ -116,6 +218,47 @@ impl UIContext {
         }
 
         let progress_bar: gtk::Scale = builder.object("progress-bar").unwrap();
+
+        let ab_repeat_area: gtk::DrawingArea = builder.object("ab-repeat-area").unwrap();
+        let ab_repeat_range = Rc::new(Cell::new((None, None)));
+        let range = ab_repeat_range.clone();
+        let scale = progress_bar.clone();
+        ab_repeat_area.set_draw_func(move |area, cr, _width, _height| {
+            let (Some(a), b) = range.get() else {
+                return;
+            };
+            let adjustment = scale.adjustment();
+            let span = adjustment.upper() - adjustment.lower();
+            if span <= 0.0 {
+                return;
+            }
+            // Positions of the slider center along the trough, which is shorter than the scale by the slider
+            // size.
+            let trough = scale.range_rect();
+            let (slider_start, slider_end) = scale.slider_range();
+            let slider_size = (slider_end - slider_start) as f64;
+            let x = |value: f64| {
+                let fraction = ((value - adjustment.lower()) / span).clamp(0.0, 1.0);
+                trough.x() as f64 + slider_size / 2.0 + fraction * (trough.width() as f64 - slider_size)
+            };
+
+            let color = area.color();
+            let (start, width) = match b {
+                Some(b) => (x(a), x(b) - x(a)),
+                // Only a thin marker until the end of the loop is set.
+                None => (x(a) - 1.0, 2.0),
+            };
+            cr.set_source_rgba(
+                color.red() as f64,
+                color.green() as f64,
+                color.blue() as f64,
+                if b.is_some() { 0.35 } else { 1.0 },
+            );
+            cr.rectangle(start, trough.y() as f64, width, trough.height() as f64);
+            let _ = cr.fill();
+        });
+        let area = ab_repeat_area.clone();
+        progress_bar.adjustment().connect_changed(move |_| area.queue_draw());
         #[allow(deprecated)]
         let volume_button: gtk::VolumeButton = builder.object("volume-button").unwrap();
 
@@ -148,6 +291,12 @@ impl UIContext {
         let button: gtk::Button = builder.object("synchronization-window-close-button").unwrap();
         button.upcast::<gtk::Actionable>().set_action_name(Some("win.close"));
 
+        let subtitle_style_window: adw::ApplicationWindow = builder.object("subtitle-style-window").unwrap();
+        let subtitle_style_widgets = SubtitleStyleWidgets::new(&builder);
+        let button: gtk::Button = builder.object("subtitle-style-reset-button").unwrap();
+        let widgets = subtitle_style_widgets.clone();
+        button.connect_clicked(move |_| widgets.set_style(&SubtitleStyle::default()));
+
         let button: gtk::Button = builder.object("audio-offset-reset-button").unwrap();
         button
             .upcast::<gtk::Actionable>()
@@ -173,6 +322,37 @@ impl UIContext {
 
         let shortcuts_window: gtk::ShortcutsWindow = builder.object("shortcuts-window").unwrap();
 
+        let playlist_view: gtk::ListView = builder.object("playlist-view").unwrap();
+        let playlist_store = gio::ListStore::new::<glib::BoxedAnyObject>();
+        let playlist_selection = gtk::SingleSelection::new(Some(playlist_store.clone()));
+        playlist_selection.set_autoselect(false);
+        playlist_view.set_model(Some(&playlist_selection));
+        playlist_view.set_factory(Some(&Self::playlist_item_factory(&gtk_app)));
+
+        let weak_app = gtk_app.downgrade();
+        playlist_view.connect_activate(move |_, position| {
+            if let Some(app) = weak_app.upgrade() {
+                app.activate_action("playlist-jump", Some(&position.to_variant()));
+            }
+        });
+
+        let key_controller = gtk::EventControllerKey::new();
+        let weak_app = gtk_app.downgrade();
+        let weak_selection = playlist_selection.downgrade();
+        key_controller.connect_key_pressed(move |_, key, _, _| {
+            if key != gdk::Key::Delete {
+                return glib::Propagation::Proceed;
+            }
+            if let (Some(app), Some(selection)) = (weak_app.upgrade(), weak_selection.upgrade()) {
+                let position = selection.selected();
+                if position != gtk::INVALID_LIST_POSITION {
+                    app.activate_action("playlist-remove", Some(&position.to_variant()));
+                }
+            }
+            glib::Propagation::Stop
+        });
+        playlist_view.add_controller(key_controller);
+
         #[cfg(not(target_os = "linux"))]
         {
             let menu: gio::Menu = builder.object("main-menu").unwrap();
@@ -199,6 +379,14 @@ impl UIContext {
                 ("video-frame-step", vec!["<Primary>n"]),
                 ("speed-increase", vec!["Page_Up"]),
                 ("speed-decrease", vec!["Page_Down"]),
+                ("bookmark-add", vec!["<Primary>b"]),
+                ("bookmark-next", vec!["<Primary>bracketright"]),
+                ("bookmark-previous", vec!["<Primary>bracketleft"]),
+                ("chapter-next", vec!["<Primary>Page_Down"]),
+                ("chapter-previous", vec!["<Primary>Page_Up"]),
+                ("ab-repeat-start", vec!["<Primary><Shift>a"]),
+                ("ab-repeat-end", vec!["<Primary><Shift>b"]),
+                ("ab-repeat-clear", vec!["<Primary><Shift>x"]),
             ];
             for (action, accels) in accels_per_action.iter() {
                 app.set_accels_for_action(&format!("app.{action}"), accels);
@@ -235,6 +423,11 @@ impl UIContext {
             window.add_css_class("devel");
         }
 
+        let toast_overlay: adw::ToastOverlay = builder.object("toast-overlay").unwrap();
+
+        let welcome_page: adw::StatusPage = builder.object("welcome-page").unwrap();
+        welcome_page.set_icon_name(Some(config::app_id()));
+
         Self {
             window,
             header_bar,
@@ -242,16 +435,33 @@ impl UIContext {
             video_renderer,
             pause_button,
             progress_bar,
+            ab_repeat_area,
+            ab_repeat_range,
             volume_button,
             toolbar_revealer,
             track_synchronization_window,
+            subtitle_style_window,
+            subtitle_style_widgets,
             shortcuts_window,
             audio_offset_entry,
             subtitle_offset_entry,
             subtitle_track_menu,
             audio_track_menu,
             video_track_menu,
+            bookmark_list_menu: builder.object("bookmark-list-menu").unwrap(),
+            chapter_list_menu: builder.object("chapter-list-menu").unwrap(),
+            window_title: builder.object("window-title").unwrap(),
             audio_visualization_menu,
+            playlist_store,
+            playlist_selection,
+            toast_overlay,
+            resume_revealer: builder.object("resume-revealer").unwrap(),
+            resume_label: builder.object("resume-label").unwrap(),
+            resume_timeout: Rc::new(RefCell::new(None)),
+            content_stack: builder.object("content-stack").unwrap(),
+            recent_box: builder.object("recent-box").unwrap(),
+            recent_list: builder.object("recent-list").unwrap(),
+            recent_menu: builder.object("recent-menu").unwrap(),
             volume_signal_handler_id: None,
             position_signal_handler_id: None,
             audio_offset_entry_signal_handler_id: None,
@@ -260,12 +470,149 @@ impl UIContext {
         }
     }
 
+    fn playlist_item_factory(app: &adw::Application) -> gtk::SignalListItemFactory {
+        let factory = gtk::SignalListItemFactory::new();
+
+        let weak_app = app.downgrade();
+        factory.connect_setup(move |_, item| {
+            let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            let icon = gtk::Image::from_icon_name("media-playback-start-symbolic");
+            let title = gtk::Label::builder()
+                .xalign(0.0)
+                .hexpand(true)
+                .ellipsize(gtk::pango::EllipsizeMode::Middle)
+                .build();
+            let duration = gtk::Label::new(None);
+            duration.add_css_class("dim-label");
+            duration.add_css_class("numeric");
+            let menu_button = gtk::MenuButton::builder()
+                .icon_name("view-more-symbolic")
+                .valign(gtk::Align::Center)
+                .build();
+            menu_button.add_css_class("flat");
+            row.append(&icon);
+            row.append(&title);
+            row.append(&duration);
+            row.append(&menu_button);
+
+            // Rows can be dragged onto each other to reorder the playlist.
+            let drag_source = gtk::DragSource::new();
+            drag_source.set_actions(gdk::DragAction::MOVE);
+            let weak_item = item.downgrade();
+            drag_source.connect_prepare(move |_, _, _| {
+                let item = weak_item.upgrade()?;
+                Some(gdk::ContentProvider::for_value(&item.position().to_value()))
+            });
+            row.add_controller(drag_source);
+
+            let drop_target = gtk::DropTarget::new(u32::static_type(), gdk::DragAction::MOVE);
+            let weak_item = item.downgrade();
+            let weak_app = weak_app.clone();
+            drop_target.connect_drop(move |_, value, _, _| {
+                let (Some(item), Some(app)) = (weak_item.upgrade(), weak_app.upgrade()) else {
+                    return false;
+                };
+                let Ok(from) = value.get::<u32>() else {
+                    return false;
+                };
+                let to = item.position();
+                if from != to && to != gtk::INVALID_LIST_POSITION {
+                    app.activate_action("playlist-move", Some(&(from, to).to_variant()));
+                }
+                true
+            });
+            row.add_controller(drop_target);
+
+            item.set_child(Some(&row));
+        });
+
+        factory.connect_bind(|_, item| {
+            let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+            let Some(object) = item.item().and_downcast::<glib::BoxedAnyObject>() else {
+                return;
+            };
+            let Some(row) = item.child().and_downcast::<gtk::Box>() else {
+                return;
+            };
+            let playlist_row = object.borrow::<PlaylistRow>();
+            let position = item.position();
+
+            let icon = row.first_child().unwrap();
+            icon.set_opacity(if playlist_row.current { 1.0 } else { 0.0 });
+
+            let title = icon.next_sibling().and_downcast::<gtk::Label>().unwrap();
+            title.set_label(&playlist_row.title);
+            title.set_tooltip_text(Some(&playlist_row.title));
+
+            let duration = title.next_sibling().and_downcast::<gtk::Label>().unwrap();
+            match playlist_row.duration {
+                Some(d) => duration.set_label(&format!("{d:.0}")),
+                None => duration.set_label(""),
+            }
+
+            let menu_button = duration.next_sibling().and_downcast::<gtk::MenuButton>().unwrap();
+            let menu = gio::Menu::new();
+            let menu_item = gio::MenuItem::new(Some(&gettext("Play next")), None);
+            menu_item.set_action_and_target_value(Some("app.playlist-play-next"), Some(&position.to_variant()));
+            menu.append_item(&menu_item);
+            let menu_item = gio::MenuItem::new(Some(&gettext("Remove")), None);
+            menu_item.set_action_and_target_value(Some("app.playlist-remove"), Some(&position.to_variant()));
+            menu.append_item(&menu_item);
+            menu_button.set_menu_model(Some(&menu));
+        });
+
+        factory
+    }
+
+    pub fn update_playlist(&self, rows: Vec<PlaylistRow>) {
+        let selected = rows.iter().position(|row| row.current);
+        let objects: Vec<glib::BoxedAnyObject> = rows.into_iter().map(glib::BoxedAnyObject::new).collect();
+        self.playlist_store.splice(0, self.playlist_store.n_items(), &objects);
+        match selected {
+            Some(index) => self.playlist_selection.set_selected(index as u32),
+            None => self.playlist_selection.set_selected(gtk::INVALID_LIST_POSITION),
+        }
+    }
+
     pub fn open_track_synchronization_window(&self) {
         let window = &self.track_synchronization_window;
         window.set_transient_for(Some(&self.window));
         window.set_visible(true);
     }
 
+    pub fn open_subtitle_style_window(&self) {
+        let window = &self.subtitle_style_window;
+        window.set_transient_for(Some(&self.window));
+        window.set_visible(true);
+    }
+
+    pub fn set_subtitle_style(&self, style: &SubtitleStyle) {
+        self.subtitle_style_widgets.set_style(style);
+    }
+
+    /// Called with the whole style whenever one of its settings is changed from the subtitle style window.
+    pub fn set_subtitle_style_changed_callback<F: Fn(SubtitleStyle) + 'static>(&self, f: F) {
+        let f = Rc::new(f);
+        let widgets = &self.subtitle_style_widgets;
+        let changed = {
+            let widgets = widgets.clone();
+            move || f(widgets.style())
+        };
+        let callback = changed.clone();
+        widgets.font_button.connect_font_desc_notify(move |_| callback());
+        let callback = changed.clone();
+        widgets.color_button.connect_rgba_notify(move |_| callback());
+        let callback = changed.clone();
+        widgets.outline_row.connect_active_notify(move |_| callback());
+        let callback = changed.clone();
+        widgets.outline_color_button.connect_rgba_notify(move |_| callback());
+        let callback = changed.clone();
+        widgets.background_scale.connect_value_changed(move |_| callback());
+        widgets.margin_row.connect_value_notify(move |_| changed());
+    }
+
     pub fn show_shortcuts(&self) {
         let window = &self.shortcuts_window;
         window.set_transient_for(Some(&self.window));
@@ -394,6 +741,24 @@ impl UIContext {
         });
     }
 
+    pub fn save_dialog<F>(&self, title: &str, initial_name: &str, f: F)
+    where
+        F: Fn(path::PathBuf) + Send + Sync + 'static,
+    {
+        let dialog = gtk::FileDialog::builder()
+            .title(title)
+            .accept_label(gettext("Save"))
+            .initial_name(initial_name)
+            .build();
+        dialog.save(Some(&self.window), gio::Cancellable::NONE, move |result| {
+            if let Ok(file) = result {
+                if let Some(path) = file.path() {
+                    f(path);
+                }
+            }
+        });
+    }
+
     pub fn start<F: Fn() + Send + Sync + 'static>(&self, f: F) {
         self.window.set_application(Some(&self.app));
         self.window.set_visible(true);
@@ -518,6 +883,11 @@ impl UIContext {
 
     pub fn set_window_title(&self, title: &str) {
         self.window.set_title(Some(title));
+        self.window_title.set_title(title);
+    }
+
+    pub fn set_window_subtitle(&self, subtitle: &str) {
+        self.window_title.set_subtitle(subtitle);
     }
 
     pub fn set_position_range_end(&self, end: f64) {
@@ -587,6 +957,65 @@ impl UIContext {
         self.video_track_menu.append_section(None, section);
     }
 
+    pub fn update_bookmark_list_menu(&self, section: &gio::Menu) {
+        self.bookmark_list_menu.remove_all();
+        self.bookmark_list_menu.append_section(None, section);
+    }
+
+    pub fn update_chapter_list_menu(&self, section: &gio::Menu) {
+        self.chapter_list_menu.remove_all();
+        self.chapter_list_menu.append_section(None, section);
+    }
+
+    /// Ticks on the progress bar at the given positions, in seconds. Bookmarks are shown above the bar and
+    /// chapters below.
+    pub fn set_progress_bar_marks(&self, bookmarks: &[f64], chapters: &[f64]) {
+        self.progress_bar.clear_marks();
+        for position in bookmarks {
+            self.progress_bar.add_mark(*position, gtk::PositionType::Top, None);
+        }
+        for position in chapters {
+            self.progress_bar.add_mark(*position, gtk::PositionType::Bottom, None);
+        }
+    }
+
+    /// Highlight the part of the progress bar looped by the A–B repeat, in seconds. Only the start is marked
+    /// while the end is unknown.
+    pub fn set_ab_repeat_range(&self, a: Option<f64>, b: Option<f64>) {
+        self.ab_repeat_range.set((a, b));
+        self.ab_repeat_area.queue_draw();
+    }
+
+    /// Ask for the label of a bookmark, which is optional. `f` is only called if the dialog is confirmed.
+    pub fn bookmark_label_dialog<F>(&self, heading: &str, confirm: &str, initial_label: &str, f: F)
+    where
+        F: Fn(Option<string::String>) + 'static,
+    {
+        let entry = gtk::Entry::builder()
+            .text(initial_label)
+            .placeholder_text(gettext("Label (optional)"))
+            .activates_default(true)
+            .build();
+        let dialog = adw::MessageDialog::builder()
+            .heading(heading)
+            .extra_child(&entry)
+            .default_response("confirm")
+            .close_response("cancel")
+            .transient_for(&self.window)
+            .modal(true)
+            .build();
+        dialog.add_response("cancel", &gettext("Cancel"));
+        dialog.add_response("confirm", confirm);
+        dialog.set_response_appearance("confirm", adw::ResponseAppearance::Suggested);
+        dialog.connect_response(None, move |_, response| {
+            if response == "confirm" {
+                let label = entry.text().trim().to_string();
+                f(Some(label).filter(|label| !label.is_empty()));
+            }
+        });
+        dialog.present();
+    }
+
     pub fn clear_audio_visualization_menu(&self) {
         self.audio_visualization_menu.remove_all();
     }
@@ -600,6 +1029,85 @@ impl UIContext {
         self.audio_visualization_menu.is_mutable()
     }
 
+    pub fn show_resume_prompt(&self, position: gst::ClockTime) {
+        let seconds = position.seconds();
+        let time = if seconds >= 3600 {
+            format!("{}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60)
+        } else {
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        };
+        self.resume_label
+            .set_label(&gettext("Resume from {}?").replace("{}", &time));
+        self.resume_revealer.set_reveal_child(true);
+
+        // The prompt goes away by itself, playback then simply continues from the start.
+        if let Some(source) = self.resume_timeout.take() {
+            source.remove();
+        }
+        let revealer = self.resume_revealer.clone();
+        let timeout = self.resume_timeout.clone();
+        let source = glib::timeout_add_seconds_local_once(*RESUME_PROMPT_TIMEOUT_SECONDS, move || {
+            timeout.take();
+            revealer.set_reveal_child(false);
+        });
+        self.resume_timeout.replace(Some(source));
+    }
+
+    pub fn hide_resume_prompt(&self) {
+        if let Some(source) = self.resume_timeout.take() {
+            source.remove();
+        }
+        self.resume_revealer.set_reveal_child(false);
+    }
+
+    /// Show the list of recently played media instead of the video.
+    pub fn show_welcome_page(&self) {
+        self.content_stack.set_visible_child_name("welcome");
+    }
+
+    pub fn show_player_page(&self) {
+        self.content_stack.set_visible_child_name("player");
+    }
+
+    pub fn update_recent_media(&self, rows: Vec<RecentRow>) {
+        self.recent_list.remove_all();
+        self.recent_menu.remove_all();
+        self.recent_box.set_visible(!rows.is_empty());
+
+        for row in rows {
+            let target = row.uri.to_variant();
+            let menu_item = gio::MenuItem::new(Some(&row.title), None);
+            menu_item.set_action_and_target_value(Some("app.open-recent"), Some(&target));
+            self.recent_menu.append_item(&menu_item);
+
+            let subtitle = match row.remaining {
+                Some(remaining) => gettext("{} left").replace("{}", &format!("{remaining:.0}")),
+                None if row.progress >= 1.0 => gettext("Watched"),
+                None => string::String::new(),
+            };
+            let progress_bar = gtk::ProgressBar::builder()
+                .fraction(row.progress)
+                .valign(gtk::Align::Center)
+                .width_request(96)
+                .build();
+            let action_row = adw::ActionRow::builder()
+                .title(&row.title)
+                .subtitle(&subtitle)
+                .use_markup(false)
+                .activatable(true)
+                .tooltip_text(&row.uri)
+                .build();
+            action_row.add_suffix(&progress_bar);
+            action_row.set_action_name(Some("app.open-recent"));
+            action_row.set_action_target_value(Some(&target));
+            self.recent_list.append(&action_row);
+        }
+    }
+
+    pub fn show_toast(&self, message: &str) {
+        self.toast_overlay.add_toast(adw::Toast::new(message));
+    }
+
     pub fn show_error_dialog(&self, report_path: Option<String>, debug: Option<String>) {
         let body = if report_path.is_some() {
             gettext("An error report was saved. If you decide to file a bug, please include the report file.")